@@ -85,7 +85,7 @@ typedef struct FnTable {
     int (*add_to_additional)(ParsedPacket *parsed_packet, const CErr **err,
                              const char *rr_str);
     int (*raw_packet)(const ParsedPacket *parsed_packet,
-                      uint8_t             raw_packet[DNS_MAX_PACKET_SIZE],
+                      uint8_t            *raw_packet,
                       size_t *raw_packet_len, size_t max_len);
     int (*question)(ParsedPacket *parsed_packet,
                     char name[DNS_MAX_HOSTNAME_LEN + 1], uint16_t *rr_type);
@@ -79,17 +79,16 @@ unsafe extern "C" fn iter_answer(
     cb: unsafe extern "C" fn(ctx: *mut c_void, section_iterator: *const SectionIterator) -> bool,
     ctx: *mut c_void,
 ) {
-    let mut it = (*parsed_packet).into_iter_answer();
-    while let Some(mut item) = it {
+    let mut it = (*parsed_packet).cursor_answer();
+    while it.advance() {
         let section_iterator = SectionIterator {
             magic: SECTION_ITERATOR_MAGIC,
             section: Section::Answer,
-            it: &mut item as *mut _ as *mut _,
+            it: &mut it as *mut _ as *mut _,
         };
         if (cb)(ctx, &section_iterator as *const _ as *const _) {
             break;
         }
-        it = item.next();
     }
 }
 
@@ -98,17 +97,16 @@ unsafe extern "C" fn iter_nameservers(
     cb: unsafe extern "C" fn(ctx: *mut c_void, section_iterator: *const SectionIterator) -> bool,
     ctx: *mut c_void,
 ) {
-    let mut it = (*parsed_packet).into_iter_nameservers();
-    while let Some(mut item) = it {
+    let mut it = (*parsed_packet).cursor_nameservers();
+    while it.advance() {
         let section_iterator = SectionIterator {
             magic: SECTION_ITERATOR_MAGIC,
             section: Section::NameServers,
-            it: &mut item as *mut _ as *mut _,
+            it: &mut it as *mut _ as *mut _,
         };
         if (cb)(ctx, &section_iterator as *const _ as *const _) {
             break;
         }
-        it = item.next();
     }
 }
 
@@ -117,17 +115,16 @@ unsafe extern "C" fn iter_additional(
     cb: unsafe extern "C" fn(ctx: *mut c_void, section_iterator: *const SectionIterator) -> bool,
     ctx: *mut c_void,
 ) {
-    let mut it = (*parsed_packet).into_iter_additional();
-    while let Some(mut item) = it {
+    let mut it = (*parsed_packet).cursor_additional();
+    while it.advance() {
         let section_iterator = SectionIterator {
             magic: SECTION_ITERATOR_MAGIC,
             section: Section::Additional,
-            it: &mut item as *mut _ as *mut _,
+            it: &mut it as *mut _ as *mut _,
         };
         if (cb)(ctx, &section_iterator as *const _ as *const _) {
             break;
         }
-        it = item.next();
     }
 }
 
@@ -136,17 +133,16 @@ unsafe extern "C" fn iter_edns(
     cb: unsafe extern "C" fn(ctx: *mut c_void, section_iterator: *const EdnsIterator<'_>) -> bool,
     ctx: *mut c_void,
 ) {
-    let mut it = (*parsed_packet).into_iter_edns();
-    while let Some(mut item) = it {
+    let mut it = (*parsed_packet).cursor_edns();
+    while it.advance() {
         let section_iterator = SectionIterator {
             magic: SECTION_ITERATOR_MAGIC,
             section: Section::Edns,
-            it: &mut item as *mut _ as *mut _,
+            it: &mut it as *mut _ as *mut _,
         };
         if (cb)(ctx, &section_iterator as *const _ as *const _) {
             break;
         }
-        it = item.next();
     }
 }
 
@@ -417,7 +413,7 @@ unsafe extern "C" fn add_to_additional(
 
 unsafe extern "C" fn raw_packet(
     parsed_packet: *const ParsedPacket,
-    raw_packet_: &mut [u8; DNS_MAX_UNCOMPRESSED_SIZE],
+    raw_packet_: *mut u8,
     raw_packet_len: *mut size_t,
     raw_packet_max_len: size_t,
 ) -> c_int {
@@ -426,7 +422,7 @@ unsafe extern "C" fn raw_packet(
     if packet_len > raw_packet_max_len {
         return -1;
     }
-    raw_packet_[..packet_len].copy_from_slice(packet);
+    slice::from_raw_parts_mut(raw_packet_, packet_len).copy_from_slice(packet);
     *raw_packet_len = packet_len;
     0
 }
@@ -571,7 +567,7 @@ pub struct FnTable {
     ) -> c_int,
     pub raw_packet: unsafe extern "C" fn(
         parsed_packet: *const ParsedPacket,
-        raw_packet_: &mut [u8; DNS_MAX_UNCOMPRESSED_SIZE],
+        raw_packet_: *mut u8,
         raw_packet_len: *mut size_t,
         raw_packet_max_len: size_t,
     ) -> c_int,
@@ -0,0 +1,129 @@
+use crate::constants::*;
+use crate::errors::*;
+use byteorder::{BigEndian, ByteOrder};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// An edns client subnet option, as defined in RFC 7871.
+/// `addr` only retains the first `source_prefix` bits of the address; the remaining
+/// bits are always zero.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ClientSubnet {
+    pub family: u16,
+    pub source_prefix: u8,
+    pub scope_prefix: u8,
+    pub addr: IpAddr,
+}
+
+impl ClientSubnet {
+    /// Builds a client subnet from an IP address, keeping only the first `source_prefix` bits.
+    /// The prefix is capped to the size of the address.
+    pub fn from_ip(ip: IpAddr, source_prefix: u8) -> Self {
+        let (family, max_prefix) = match ip {
+            IpAddr::V4(_) => (DNS_ECS_FAMILY_IPV4, 32),
+            IpAddr::V6(_) => (DNS_ECS_FAMILY_IPV6, 128),
+        };
+        let source_prefix = source_prefix.min(max_prefix);
+        let addr = match ip {
+            IpAddr::V4(ip) => {
+                let mut octets = ip.octets();
+                Self::mask(&mut octets, source_prefix);
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            IpAddr::V6(ip) => {
+                let mut octets = ip.octets();
+                Self::mask(&mut octets, source_prefix);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+        };
+        ClientSubnet {
+            family,
+            source_prefix,
+            scope_prefix: 0,
+            addr,
+        }
+    }
+
+    /// Decodes the data of an untrusted client subnet option.
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < DNS_ECS_HEADER_SIZE {
+            bail!(DSError::InvalidPacket("Client subnet option too short"));
+        }
+        let family = BigEndian::read_u16(data);
+        let source_prefix = data[2];
+        let scope_prefix = data[3];
+        let max_prefix = match family {
+            DNS_ECS_FAMILY_IPV4 => 32,
+            DNS_ECS_FAMILY_IPV6 => 128,
+            _ => bail!(DSError::WrongAddressFamily),
+        };
+        if source_prefix > max_prefix || scope_prefix > max_prefix {
+            bail!(DSError::InvalidPacket("Client subnet prefix too long"));
+        }
+        let addr_bytes = &data[DNS_ECS_HEADER_SIZE..];
+        if addr_bytes.len() != Self::addr_len(source_prefix) {
+            bail!(DSError::InvalidPacket(
+                "Client subnet address doesn't match the source prefix"
+            ));
+        }
+        let mut octets = [0u8; 16];
+        octets[..addr_bytes.len()].copy_from_slice(addr_bytes);
+        let mut masked = octets;
+        Self::mask(&mut masked, source_prefix);
+        if masked != octets {
+            bail!(DSError::InvalidPacket(
+                "Client subnet address has bits set past the source prefix"
+            ));
+        }
+        let addr = if family == DNS_ECS_FAMILY_IPV4 {
+            IpAddr::V4(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]))
+        } else {
+            IpAddr::V6(Ipv6Addr::from(octets))
+        };
+        Ok(ClientSubnet {
+            family,
+            source_prefix,
+            scope_prefix,
+            addr,
+        })
+    }
+
+    /// Encodes the client subnet as the data of an edns option.
+    /// The address is truncated to the source prefix.
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let (mut octets, max_prefix) = match (self.family, self.addr) {
+            (DNS_ECS_FAMILY_IPV4, IpAddr::V4(ip)) => {
+                let mut octets = [0u8; 16];
+                octets[..4].copy_from_slice(&ip.octets());
+                (octets, 32)
+            }
+            (DNS_ECS_FAMILY_IPV6, IpAddr::V6(ip)) => (ip.octets(), 128),
+            _ => bail!(DSError::WrongAddressFamily),
+        };
+        if self.source_prefix > max_prefix || self.scope_prefix > max_prefix {
+            bail!(DSError::InvalidPacket("Client subnet prefix too long"));
+        }
+        Self::mask(&mut octets, self.source_prefix);
+        let addr_len = Self::addr_len(self.source_prefix);
+        let mut data = vec![0u8; DNS_ECS_HEADER_SIZE];
+        BigEndian::write_u16(&mut data, self.family);
+        data[2] = self.source_prefix;
+        data[3] = self.scope_prefix;
+        data.extend_from_slice(&octets[..addr_len]);
+        Ok(data)
+    }
+
+    /// Number of address bytes required to store `prefix` bits.
+    #[inline]
+    fn addr_len(prefix: u8) -> usize {
+        (prefix as usize + 7) >> 3
+    }
+
+    /// Clears all the bits past the first `prefix` bits.
+    fn mask(octets: &mut [u8], prefix: u8) {
+        let prefix = prefix as usize;
+        for (i, octet) in octets.iter_mut().enumerate() {
+            let bits = prefix.saturating_sub(i * 8).min(8);
+            *octet &= !(0xffu16 >> bits) as u8;
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use crate::constants::*;
 use crate::dns_sector::*;
 use crate::errors::*;
+use crate::parse_options::*;
+use crate::parsed_packet_ref::*;
 use crate::rr_iterator::*;
 use byteorder::{BigEndian, ByteOrder};
 use std::cmp;
@@ -26,44 +28,63 @@ impl Compress {
     /// compressed names, checks for label lengths, checks for truncated names and checks for
     /// cycles.
     /// Returns the location right after the name.
-    pub fn check_compressed_name(packet: &[u8], mut offset: usize) -> Result<usize, Error> {
+    pub fn check_compressed_name(packet: &[u8], mut offset: usize) -> Result<usize, ParseError> {
         let packet_len = packet.len();
         let mut name_len = 0;
         let (mut barrier_offset, mut lowest_offset, mut final_offset) = (packet_len, offset, None);
         let mut refs_allowed = DNS_MAX_HOSTNAME_INDIRECTIONS;
         if offset >= packet_len {
-            bail!(DSError::InternalError("Offset outside packet boundaries"));
+            return Err(ParseError::new(
+                DSError::InternalError("Offset outside packet boundaries"),
+                offset,
+            ));
         }
         if 1 > packet_len - offset {
-            bail!(DSError::InvalidName("Empty name"));
+            return Err(ParseError::new(DSError::InvalidName("Empty name"), offset));
         }
         loop {
             if offset >= barrier_offset {
                 if offset >= packet_len {
-                    bail!(DSError::InvalidName("Truncated name"));
+                    return Err(ParseError::new(
+                        DSError::InvalidName("Truncated name"),
+                        offset,
+                    ));
                 }
-                bail!(DSError::InvalidName("Cycle"));
+                return Err(ParseError::new(DSError::InvalidName("Cycle"), offset));
             }
             let label_len = match packet[offset] {
                 len if len & 0xc0 == 0xc0 => {
                     if refs_allowed <= 0 {
-                        bail!(DSError::InvalidName("Too many indirections"));
+                        return Err(ParseError::new(
+                            DSError::InvalidName("Too many indirections"),
+                            offset,
+                        ));
                     }
                     refs_allowed -= 1;
                     if 2 > packet_len - offset {
-                        bail!(DSError::InvalidName("Invalid internal offset"));
+                        return Err(ParseError::new(
+                            DSError::InvalidName("Invalid internal offset"),
+                            offset,
+                        ));
                     }
                     let ref_offset =
                         ((((len & 0x3f) as u16) << 8) | (packet[offset + 1]) as u16) as usize;
                     if ref_offset == offset || ref_offset >= lowest_offset {
-                        bail!(DSError::InvalidName("Forward/self reference"));
+                        return Err(ParseError::new(
+                            DSError::InvalidName("Forward/self reference"),
+                            offset,
+                        ));
                     }
                     if packet[ref_offset] & 0xc0 == 0xc0 {
-                        bail!(DSError::InvalidName("Double reference"));
+                        return Err(ParseError::new(
+                            DSError::InvalidName("Double reference"),
+                            offset,
+                        ));
                     }
                     if packet[ref_offset] < 1 {
-                        bail!(DSError::InvalidName(
-                            "Reference to a name that cannot be compressed"
+                        return Err(ParseError::new(
+                            DSError::InvalidName("Reference to a name that cannot be compressed"),
+                            offset,
                         ));
                     }
                     final_offset = final_offset.or_else(|| Some(offset + 2));
@@ -72,15 +93,26 @@ impl Compress {
                     lowest_offset = ref_offset;
                     continue;
                 }
-                len if len > 0x3f => bail!(DSError::InvalidName("Label length too long")),
+                len if len > 0x3f => {
+                    return Err(ParseError::new(
+                        DSError::InvalidName("Label length too long"),
+                        offset,
+                    ))
+                }
                 len => len as usize,
             };
             if label_len >= packet_len - offset {
-                bail!(DSError::InvalidName("Out-of-bounds name"));
+                return Err(ParseError::new(
+                    DSError::InvalidName("Out-of-bounds name"),
+                    offset,
+                ));
             }
             name_len += label_len + 1;
             if name_len > DNS_MAX_HOSTNAME_LEN {
-                bail!(DSError::InvalidName("Name too long"));
+                return Err(ParseError::new(
+                    DSError::InvalidName("Name too long"),
+                    offset,
+                ));
             }
             offset += label_len + 1;
             if label_len == 0 {
@@ -128,7 +160,7 @@ impl Compress {
     }
 
     /// Uncompresses trusted record's data and puts the result into `name`.
-    fn uncompress_rdata(
+    pub(crate) fn uncompress_rdata(
         mut uncompressed: &mut Vec<u8>,
         raw: RRRaw<'_>,
         rr_type: Option<u16>,
@@ -187,12 +219,91 @@ impl Compress {
                     new_rdlen as u16,
                 );
             }
+            Some(x) if x == Type::MINFO.into() => {
+                let offset = uncompressed.len();
+                uncompressed.extend_from_slice(&rdata[..DNS_RR_HEADER_SIZE]);
+                let u1 = Compress::copy_uncompressed_name(
+                    uncompressed,
+                    packet,
+                    offset_rdata + DNS_RR_HEADER_SIZE,
+                );
+                let u2 = Compress::copy_uncompressed_name(uncompressed, packet, u1.final_offset);
+                let new_rdlen = u1.name_len + u2.name_len;
+                BigEndian::write_u16(
+                    &mut uncompressed[offset + DNS_RR_RDLEN_OFFSET..],
+                    new_rdlen as u16,
+                );
+            }
+            Some(x) if x == Type::NAPTR.into() => {
+                let mut prefix_len = 4;
+                for _ in 0..3 {
+                    prefix_len += 1 + rdata[DNS_RR_HEADER_SIZE + prefix_len] as usize;
+                }
+                Self::uncompress_rdata_names(uncompressed, raw, rr_rdlen.unwrap(), prefix_len, 1);
+            }
+            Some(x) if Self::rfc3597_rdata_names(x).is_some() => {
+                let (prefix_len, names) = Self::rfc3597_rdata_names(x).unwrap();
+                Self::uncompress_rdata_names(
+                    uncompressed,
+                    raw,
+                    rr_rdlen.unwrap(),
+                    prefix_len,
+                    names,
+                );
+            }
             _ => {
                 uncompressed.extend_from_slice(&rdata[..DNS_RR_HEADER_SIZE + rr_rdlen.unwrap()]);
             }
         }
     }
 
+    /// For record types whose names must not be compressed by senders, but should be
+    /// decompressed by receivers (RFC 3597 section 4), returns the length of the fixed-size
+    /// fields preceding the names, and the number of names.
+    /// `NAPTR` records are not included, since their names follow variable-length fields.
+    pub(crate) fn rfc3597_rdata_names(rr_type: u16) -> Option<(usize, usize)> {
+        match rr_type {
+            x if x == Type::RP.into() => Some((0, 2)),
+            x if x == Type::AFSDB.into() || x == Type::RT.into() => Some((2, 1)),
+            x if x == Type::SIG.into() => Some((18, 1)),
+            x if x == Type::NXT.into() => Some((0, 1)),
+            x if x == Type::PX.into() => Some((2, 2)),
+            x if x == Type::SRV.into() => Some((6, 1)),
+            _ => None,
+        }
+    }
+
+    /// Uncompresses trusted record's data made of `prefix_len` bytes of fixed-size fields,
+    /// followed by `names` names, and optionally by other fields.
+    fn uncompress_rdata_names(
+        uncompressed: &mut Vec<u8>,
+        raw: RRRaw<'_>,
+        rr_rdlen: usize,
+        prefix_len: usize,
+        names: usize,
+    ) {
+        let packet = &raw.packet;
+        let offset_rdata = raw.name_end;
+        let offset = uncompressed.len();
+        uncompressed.extend_from_slice(
+            &packet[offset_rdata..offset_rdata + DNS_RR_HEADER_SIZE + prefix_len],
+        );
+        let mut name_offset = offset_rdata + DNS_RR_HEADER_SIZE + prefix_len;
+        let mut new_rdlen = prefix_len;
+        for _ in 0..names {
+            let u = Compress::copy_uncompressed_name(uncompressed, packet, name_offset);
+            new_rdlen += u.name_len;
+            name_offset = u.final_offset;
+        }
+        let rdata_end = offset_rdata + DNS_RR_HEADER_SIZE + rr_rdlen;
+        uncompressed.extend_from_slice(&packet[name_offset..rdata_end]);
+        new_rdlen += rdata_end - name_offset;
+        BigEndian::write_u16(
+            &mut uncompressed[offset + DNS_RR_RDLEN_OFFSET..],
+            new_rdlen as u16,
+        );
+    }
+
     /// Compresses trusted record's data and puts the result into `compressed`.
     pub fn compress_rdata(
         mut dict: &mut SuffixDict,
@@ -261,192 +372,123 @@ impl Compress {
                     new_rdlen as u16,
                 );
             }
+            Some(x) if x == Type::MINFO.into() => {
+                let offset = compressed.len();
+                compressed.extend_from_slice(&rdata[..DNS_RR_HEADER_SIZE]);
+                let u1 = Compress::copy_compressed_name(
+                    dict,
+                    compressed,
+                    packet,
+                    offset_rdata + DNS_RR_HEADER_SIZE,
+                );
+                let u2 = Compress::copy_compressed_name(dict, compressed, packet, u1.final_offset);
+                let new_rdlen = u1.name_len + u2.name_len;
+                BigEndian::write_u16(
+                    &mut compressed[offset + DNS_RR_RDLEN_OFFSET..],
+                    new_rdlen as u16,
+                );
+            }
             _ => {
                 compressed.extend_from_slice(&rdata[..DNS_RR_HEADER_SIZE + rr_rdlen.unwrap()]);
             }
         }
     }
 
+    /// Uncompresses a trusted packet, and returns the location of the record that was
+    /// at `ref_offset` in the original packet.
     pub fn uncompress_with_previous_offset(
         packet: &[u8],
         ref_offset: usize,
     ) -> Result<(Vec<u8>, usize), Error> {
-        let packet = packet.to_owned(); // XXX - TODO: use `ParsedPacket` directly after having removed its dependency on `dns_sector`
+        let mut uncompressed = Vec::new();
+        let new_offset =
+            Self::uncompress_with_previous_offset_into(packet, ref_offset, &mut uncompressed)?;
+        Ok((uncompressed, new_offset))
+    }
+
+    /// Uncompresses a trusted packet into `uncompressed`, reusing its storage, and returns
+    /// the location of the record that was at `ref_offset` in the original packet.
+    pub fn uncompress_with_previous_offset_into(
+        packet: &[u8],
+        ref_offset: usize,
+        uncompressed: &mut Vec<u8>,
+    ) -> Result<usize, Error> {
         if packet.len() < DNS_HEADER_SIZE {
             bail!(DSError::PacketTooSmall);
         }
+        let parsed_packet =
+            DNSSector::from_slice(packet).parse_ref_with(&ParseOptions::internal())?;
         let mut new_offset = None;
-        let mut uncompressed = Vec::new();
+        uncompressed.clear();
         uncompressed.extend_from_slice(&packet[..DNS_HEADER_SIZE]);
-        let mut parsed_packet = DNSSector::new(packet)?.parse()?;
-        {
-            let mut it = parsed_packet.into_iter_question();
-            while let Some(item) = it {
-                if Some(ref_offset) == item.offset() {
-                    new_offset = Some(uncompressed.len());
-                }
-                item.copy_raw_name(&mut uncompressed);
-                Self::uncompress_rdata(&mut uncompressed, item.raw(), None, None);
-                it = item.next();
-            }
-        }
-        {
-            let mut it = parsed_packet.into_iter_answer();
-            while let Some(item) = it {
-                if Some(ref_offset) == item.offset() {
-                    new_offset = Some(uncompressed.len());
-                }
-                item.copy_raw_name(&mut uncompressed);
-                Self::uncompress_rdata(
-                    &mut uncompressed,
-                    item.raw(),
-                    Some(item.rr_type()),
-                    Some(item.rr_rdlen()),
-                );
-                it = item.next();
-            }
-        }
-        {
-            let mut it = parsed_packet.into_iter_nameservers();
-            while let Some(item) = it {
-                if Some(ref_offset) == item.offset() {
-                    new_offset = Some(uncompressed.len());
-                }
-                item.copy_raw_name(&mut uncompressed);
-                Self::uncompress_rdata(
-                    &mut uncompressed,
-                    item.raw(),
-                    Some(item.rr_type()),
-                    Some(item.rr_rdlen()),
-                );
-                it = item.next();
-            }
-        }
-        {
-            let mut it = parsed_packet.into_iter_additional_including_opt();
-            while let Some(item) = it {
-                if Some(ref_offset) == item.offset() {
-                    new_offset = Some(uncompressed.len());
-                }
-                item.copy_raw_name(&mut uncompressed);
-                Self::uncompress_rdata(
-                    &mut uncompressed,
-                    item.raw(),
-                    Some(item.rr_type()),
-                    Some(item.rr_rdlen()),
-                );
-                it = item.next_including_opt();
+        for item in Self::iter_records(&parsed_packet) {
+            if ref_offset == item.offset() {
+                new_offset = Some(uncompressed.len());
             }
+            item.copy_raw_name(uncompressed);
+            let (rr_type, rr_rdlen) = Self::rr_type_and_rdlen(&item);
+            Self::uncompress_rdata(uncompressed, item.raw(), rr_type, rr_rdlen);
         }
         if ref_offset == parsed_packet.packet().len() {
             new_offset = Some(uncompressed.len());
         }
-        Ok((
-            uncompressed,
-            new_offset.expect("Previous offset not found at a record boundary"),
-        ))
+        Ok(new_offset.expect("Previous offset not found at a record boundary"))
     }
 
     pub fn uncompress(packet: &[u8]) -> Result<Vec<u8>, Error> {
         Self::uncompress_with_previous_offset(packet, DNS_HEADER_SIZE).map(|x| x.0)
     }
 
+    /// Uncompresses a trusted packet into `uncompressed`, reusing its storage.
+    pub fn uncompress_into(packet: &[u8], uncompressed: &mut Vec<u8>) -> Result<(), Error> {
+        Self::uncompress_with_previous_offset_into(packet, DNS_HEADER_SIZE, uncompressed)
+            .map(|_| {})
+    }
+
     pub fn compress(packet: &[u8]) -> Result<Vec<u8>, Error> {
-        let packet = packet.to_owned(); // XXX - TODO: use `ParsedPacket` directly after having removed its dependency on `dns_sector`
+        let mut compressed = Vec::new();
+        Self::compress_into(packet, &mut compressed)?;
+        Ok(compressed)
+    }
+
+    /// Compresses a trusted, uncompressed packet into `compressed`, reusing its storage.
+    pub fn compress_into(packet: &[u8], compressed: &mut Vec<u8>) -> Result<(), Error> {
         if packet.len() < DNS_HEADER_SIZE {
             bail!(DSError::PacketTooSmall);
         }
-        let mut compressed = Vec::new();
+        let parsed_packet =
+            DNSSector::from_slice(packet).parse_ref_with(&ParseOptions::internal())?;
+        compressed.clear();
         compressed.extend_from_slice(&packet[..DNS_HEADER_SIZE]);
-        let mut parsed_packet = DNSSector::new(packet)?.parse()?;
         let mut dict = SuffixDict::new();
-        {
-            let mut it = parsed_packet.into_iter_question();
-            while let Some(item) = it {
-                {
-                    let mut raw = item.raw();
-                    raw.offset = Self::copy_compressed_name(
-                        &mut dict,
-                        &mut compressed,
-                        raw.packet,
-                        raw.offset,
-                    )
-                    .final_offset;
-                    Self::compress_rdata(&mut dict, &mut compressed, raw, None, None);
-                }
-                it = item.next();
-            }
+        for item in Self::iter_records(&parsed_packet) {
+            let mut raw = item.raw();
+            raw.offset = Self::copy_compressed_name(&mut dict, compressed, raw.packet, raw.offset)
+                .final_offset;
+            let (rr_type, rr_rdlen) = Self::rr_type_and_rdlen(&item);
+            Self::compress_rdata(&mut dict, compressed, raw, rr_type, rr_rdlen);
         }
-        {
-            let mut it = parsed_packet.into_iter_answer();
-            while let Some(item) = it {
-                {
-                    let mut raw = item.raw();
-                    raw.offset = Self::copy_compressed_name(
-                        &mut dict,
-                        &mut compressed,
-                        raw.packet,
-                        raw.offset,
-                    )
-                    .final_offset;
-                    Self::compress_rdata(
-                        &mut dict,
-                        &mut compressed,
-                        raw,
-                        Some(item.rr_type()),
-                        Some(item.rr_rdlen()),
-                    );
-                }
-                it = item.next();
-            }
-        }
-        {
-            let mut it = parsed_packet.into_iter_nameservers();
-            while let Some(item) = it {
-                {
-                    let mut raw = item.raw();
-                    raw.offset = Self::copy_compressed_name(
-                        &mut dict,
-                        &mut compressed,
-                        raw.packet,
-                        raw.offset,
-                    )
-                    .final_offset;
-                    Self::compress_rdata(
-                        &mut dict,
-                        &mut compressed,
-                        raw,
-                        Some(item.rr_type()),
-                        Some(item.rr_rdlen()),
-                    );
-                }
-                it = item.next();
-            }
-        }
-        {
-            let mut it = parsed_packet.into_iter_additional_including_opt();
-            while let Some(item) = it {
-                {
-                    let mut raw = item.raw();
-                    raw.offset = Self::copy_compressed_name(
-                        &mut dict,
-                        &mut compressed,
-                        raw.packet,
-                        raw.offset,
-                    )
-                    .final_offset;
-                    Self::compress_rdata(
-                        &mut dict,
-                        &mut compressed,
-                        raw,
-                        Some(item.rr_type()),
-                        Some(item.rr_rdlen()),
-                    );
-                }
-                it = item.next();
-            }
+        Ok(())
+    }
+
+    /// Iterates over all the records of a packet, including the OPT record.
+    fn iter_records<'a>(
+        parsed_packet: &ParsedPacketRef<'a>,
+    ) -> impl Iterator<Item = RecordRef<'a>> {
+        parsed_packet
+            .iter_question()
+            .chain(parsed_packet.iter_answer())
+            .chain(parsed_packet.iter_nameservers())
+            .chain(parsed_packet.iter_additional_including_opt())
+    }
+
+    /// Returns the type and the data length of a record, or `None` for questions.
+    fn rr_type_and_rdlen(item: &RecordRef<'_>) -> (Option<u16>, Option<usize>) {
+        if item.section() == Section::Question {
+            (None, None)
+        } else {
+            (Some(item.rr_type()), Some(item.rdata().len()))
         }
-        Ok(compressed)
     }
 
     /// Returns the total length of a raw name *without decompressing it*, including the final `0` label length.
@@ -564,7 +606,18 @@ impl Compress {
         packet: &[u8],
         offset: usize,
     ) -> CompressedNameResult {
-        Self::copy_compressed_name_with_base_offset(dict, compressed, packet, offset, 0)
+        let base_offset = compressed.len();
+        let res = Self::copy_compressed_name_with_base_offset(
+            dict,
+            compressed,
+            &packet[offset..],
+            0,
+            base_offset,
+        );
+        CompressedNameResult {
+            name_len: res.name_len,
+            final_offset: offset + res.final_offset,
+        }
     }
 }
 
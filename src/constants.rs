@@ -60,6 +60,37 @@ pub const DNS_EDNS_RR_RDLEN_OFFSET: usize = 2;
 /// Size of the header of an extended RR.
 pub const DNS_EDNS_RR_HEADER_SIZE: usize = 4;
 
+/// Size of the fixed part of an edns client subnet option (family and prefixes).
+pub const DNS_ECS_HEADER_SIZE: usize = 4;
+
+// Address families for edns client subnet options
+pub const DNS_ECS_FAMILY_IPV4: u16 = 1;
+pub const DNS_ECS_FAMILY_IPV6: u16 = 2;
+
+/// Block size queries are padded to, as recommended in RFC 8467.
+pub const DNS_PADDING_QUERY_BLOCK_SIZE: usize = 128;
+
+/// Block size responses are padded to, as recommended in RFC 8467.
+pub const DNS_PADDING_RESPONSE_BLOCK_SIZE: usize = 468;
+
+/// Size of a client cookie.
+pub const DNS_CLIENT_COOKIE_SIZE: usize = 8;
+
+/// Minimum size of a server cookie.
+pub const DNS_SERVER_COOKIE_MIN_SIZE: usize = 8;
+
+/// Maximum size of a server cookie.
+pub const DNS_SERVER_COOKIE_MAX_SIZE: usize = 32;
+
+/// Size of the interoperable server cookies defined in RFC 9018.
+pub const DNS_SERVER_COOKIE_SIZE: usize = 16;
+
+/// Version of the interoperable server cookies defined in RFC 9018.
+pub const DNS_SERVER_COOKIE_VERSION: u8 = 1;
+
+/// Size of the info code of an extended DNS error.
+pub const DNS_EDE_INFO_CODE_SIZE: usize = 2;
+
 /// Offset to the transaction ID, from the beginning of a DNS packet
 pub const DNS_TID_OFFSET: usize = 0;
 
@@ -79,12 +110,26 @@ pub const DNS_FLAG_AD: u32 = 1 << 5;
 pub const DNS_FLAG_CD: u32 = 1 << 4;
 pub const DNS_FLAG_DO: u32 = 1 << 31;
 
-/// Maximum size of an uncompressed packet
+/// Maximum payload size for UDP packets, when edns is not being used
+pub const DNS_DEFAULT_MAX_PAYLOAD: usize = 512;
+
+/// Maximum payload size advertised in OPT records created without an explicit size
+pub const DNS_DEFAULT_EDNS_PAYLOAD: u16 = 1232;
+
+/// Default maximum size a packet can grow to when it is modified -
+/// See `ParsedPacket::set_max_packet_size()`
 pub const DNS_MAX_UNCOMPRESSED_SIZE: usize = 8192;
 
-/// Maximum size of a compressed packet
+/// Maximum size of a compressed packet sent over UDP
 pub const DNS_MAX_COMPRESSED_SIZE: usize = 4096;
 
+/// Maximum size of a packet sent over a stream transport (TCP or TLS),
+/// which is also the largest possible packet size
+pub const DNS_MAX_TCP_SIZE: usize = 65535;
+
+/// Size of the length prefix of packets sent over a stream transport
+pub const DNS_TCP_LENGTH_PREFIX_SIZE: usize = 2;
+
 /// DNS query class
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Class {
@@ -105,8 +150,8 @@ impl Class {
     pub fn from_string(rr_type_str: &str) -> Result<Class, Error> {
         match rr_type_str {
             s if s.eq_ignore_ascii_case("IN") => Ok(Class::IN),
-            s if s.eq_ignore_ascii_case("CH") => Ok(Class::CH),
-            s if s.eq_ignore_ascii_case("HS") => Ok(Class::HS),
+            s if s.eq_ignore_ascii_case("CH") || s.eq_ignore_ascii_case("CHAOS") => Ok(Class::CH),
+            s if s.eq_ignore_ascii_case("HS") || s.eq_ignore_ascii_case("HESIOD") => Ok(Class::HS),
             s if s.eq_ignore_ascii_case("NONE") => Ok(Class::NONE),
             s if s.eq_ignore_ascii_case("ANY") => Ok(Class::ANY),
             _ => bail!(DSError::UnsupportedRRClass(rr_type_str.to_owned())),
@@ -179,6 +224,8 @@ pub enum Type {
     CDNSKEY = 60,    // RFC 7344
     OPENPGPKEY = 61, // RFC 7929
     CSYNC = 62,      // RFC 7477
+    SVCB = 64,       // RFC 9460
+    HTTPS = 65,      // RFC 9460
     SPF = 99,        // RFC 4408
     UINFO = 100,
     UID = 101,
@@ -239,8 +286,13 @@ pub enum EdnsOption {
     DHU = 6,
     N3U = 7,
     CLIENT_SUBNET = 8,
+    EXPIRE = 9,
+    COOKIE = 10,
     KEEPALIVE = 11,
     PADDING = 12,
+    CHAIN = 13,
+    KEY_TAG = 14,
+    EDE = 15,
 }
 
 impl From<EdnsOption> for u16 {
@@ -249,6 +301,89 @@ impl From<EdnsOption> for u16 {
     }
 }
 
+/// Extended DNS error codes (RFC 8914)
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ExtendedError {
+    OTHER = 0,
+    UNSUPPORTED_DNSKEY_ALGORITHM = 1,
+    UNSUPPORTED_DS_DIGEST_TYPE = 2,
+    STALE_ANSWER = 3,
+    FORGED_ANSWER = 4,
+    DNSSEC_INDETERMINATE = 5,
+    DNSSEC_BOGUS = 6,
+    SIGNATURE_EXPIRED = 7,
+    SIGNATURE_NOT_YET_VALID = 8,
+    DNSKEY_MISSING = 9,
+    RRSIGS_MISSING = 10,
+    NO_ZONE_KEY_BIT_SET = 11,
+    NSEC_MISSING = 12,
+    CACHED_ERROR = 13,
+    NOT_READY = 14,
+    BLOCKED = 15,
+    CENSORED = 16,
+    FILTERED = 17,
+    PROHIBITED = 18,
+    STALE_NXDOMAIN_ANSWER = 19,
+    NOT_AUTHORITATIVE = 20,
+    NOT_SUPPORTED = 21,
+    NO_REACHABLE_AUTHORITY = 22,
+    NETWORK_ERROR = 23,
+    INVALID_DATA = 24,
+    SIGNATURE_EXPIRED_BEFORE_VALID = 25,
+    TOO_EARLY = 26,
+    UNSUPPORTED_NSEC3_ITERATIONS_VALUE = 27,
+    UNABLE_TO_CONFORM_TO_POLICY = 28,
+    SYNTHESIZED = 29,
+    INVALID_QUERY_TYPE = 30,
+}
+
+impl From<ExtendedError> for u16 {
+    fn from(v: ExtendedError) -> u16 {
+        v as u16
+    }
+}
+
+impl ExtendedError {
+    pub fn from_u16(info_code: u16) -> Option<ExtendedError> {
+        let error = match info_code {
+            0 => ExtendedError::OTHER,
+            1 => ExtendedError::UNSUPPORTED_DNSKEY_ALGORITHM,
+            2 => ExtendedError::UNSUPPORTED_DS_DIGEST_TYPE,
+            3 => ExtendedError::STALE_ANSWER,
+            4 => ExtendedError::FORGED_ANSWER,
+            5 => ExtendedError::DNSSEC_INDETERMINATE,
+            6 => ExtendedError::DNSSEC_BOGUS,
+            7 => ExtendedError::SIGNATURE_EXPIRED,
+            8 => ExtendedError::SIGNATURE_NOT_YET_VALID,
+            9 => ExtendedError::DNSKEY_MISSING,
+            10 => ExtendedError::RRSIGS_MISSING,
+            11 => ExtendedError::NO_ZONE_KEY_BIT_SET,
+            12 => ExtendedError::NSEC_MISSING,
+            13 => ExtendedError::CACHED_ERROR,
+            14 => ExtendedError::NOT_READY,
+            15 => ExtendedError::BLOCKED,
+            16 => ExtendedError::CENSORED,
+            17 => ExtendedError::FILTERED,
+            18 => ExtendedError::PROHIBITED,
+            19 => ExtendedError::STALE_NXDOMAIN_ANSWER,
+            20 => ExtendedError::NOT_AUTHORITATIVE,
+            21 => ExtendedError::NOT_SUPPORTED,
+            22 => ExtendedError::NO_REACHABLE_AUTHORITY,
+            23 => ExtendedError::NETWORK_ERROR,
+            24 => ExtendedError::INVALID_DATA,
+            25 => ExtendedError::SIGNATURE_EXPIRED_BEFORE_VALID,
+            26 => ExtendedError::TOO_EARLY,
+            27 => ExtendedError::UNSUPPORTED_NSEC3_ITERATIONS_VALUE,
+            28 => ExtendedError::UNABLE_TO_CONFORM_TO_POLICY,
+            29 => ExtendedError::SYNTHESIZED,
+            30 => ExtendedError::INVALID_QUERY_TYPE,
+            _ => return None,
+        };
+        Some(error)
+    }
+}
+
 /// DNS return codes
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Rcode {
@@ -263,6 +398,19 @@ pub enum Rcode {
     NXRRSET = 8,
     NOTAUTH = 9,
     NOTZONE = 10,
+    BADVERS = 16,
+    BADKEY = 17,
+    BADTIME = 18,
+    BADMODE = 19,
+    BADNAME = 20,
+    BADALG = 21,
+    BADTRUNC = 22,
+    BADCOOKIE = 23,
+}
+
+impl Rcode {
+    /// `BADSIG` shares its value with `BADVERS`; it is only used in TSIG records.
+    pub const BADSIG: Rcode = Rcode::BADVERS;
 }
 
 impl From<Rcode> for u8 {
@@ -271,6 +419,12 @@ impl From<Rcode> for u8 {
     }
 }
 
+impl From<Rcode> for u16 {
+    fn from(v: Rcode) -> u16 {
+        v as u16
+    }
+}
+
 /// DNS opcodes
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Opcode {
@@ -288,7 +442,7 @@ impl From<Opcode> for u8 {
 }
 
 /// DNS packet section
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Section {
     Question,
     Answer,
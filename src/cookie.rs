@@ -0,0 +1,132 @@
+use crate::constants::*;
+use crate::errors::*;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use siphasher::sip::SipHasher24;
+use std::hash::Hasher;
+use std::net::IpAddr;
+
+/// A DNS cookie option (RFC 7873): a client cookie, optionally followed by a server cookie.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cookie {
+    pub client: [u8; DNS_CLIENT_COOKIE_SIZE],
+    pub server: Option<Vec<u8>>,
+}
+
+impl Cookie {
+    /// Decodes the data of an untrusted cookie option.
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        let server_len = match data.len().checked_sub(DNS_CLIENT_COOKIE_SIZE) {
+            None => bail!(DSError::InvalidPacket("Cookie too short")),
+            Some(server_len) => server_len,
+        };
+        if server_len != 0
+            && !(DNS_SERVER_COOKIE_MIN_SIZE..=DNS_SERVER_COOKIE_MAX_SIZE).contains(&server_len)
+        {
+            bail!(DSError::InvalidPacket("Invalid server cookie length"));
+        }
+        let mut client = [0u8; DNS_CLIENT_COOKIE_SIZE];
+        client.copy_from_slice(&data[..DNS_CLIENT_COOKIE_SIZE]);
+        let server = if server_len == 0 {
+            None
+        } else {
+            Some(data[DNS_CLIENT_COOKIE_SIZE..].to_vec())
+        };
+        Ok(Cookie { client, server })
+    }
+
+    /// Encodes the cookie as the data of an edns option.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = self.client.to_vec();
+        if let Some(ref server) = self.server {
+            data.extend_from_slice(server);
+        }
+        data
+    }
+}
+
+/// Result of the verification of a cookie sent by a client.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CookieStatus {
+    /// Only a client cookie was sent.
+    ClientOnly,
+    /// The server cookie was issued by us, for that client, and hasn't expired.
+    Valid,
+    /// The server cookie wasn't issued by us, was issued for a different client, or has expired.
+    Invalid,
+}
+
+/// Server cookie generation and verification, using the interoperable
+/// construction from RFC 9018.
+/// Timestamps are expressed in seconds since the UNIX epoch, modulo 2^32.
+#[derive(Clone, Debug)]
+pub struct CookieConfig {
+    pub secret: [u8; 16],
+    pub max_age: u32,
+    pub max_clock_skew: u32,
+}
+
+impl CookieConfig {
+    /// Creates a new configuration, with the lifetime and the clock skew recommended in RFC 9018.
+    pub fn new(secret: [u8; 16]) -> Self {
+        CookieConfig {
+            secret,
+            max_age: 3600,
+            max_clock_skew: 300,
+        }
+    }
+
+    /// Computes a server cookie for a given client cookie and client IP address.
+    pub fn server_cookie(
+        &self,
+        client: &[u8; DNS_CLIENT_COOKIE_SIZE],
+        client_ip: IpAddr,
+        timestamp: u32,
+    ) -> [u8; DNS_SERVER_COOKIE_SIZE] {
+        let mut server = [0u8; DNS_SERVER_COOKIE_SIZE];
+        server[0] = DNS_SERVER_COOKIE_VERSION;
+        BigEndian::write_u32(&mut server[4..], timestamp);
+        let mut hasher = SipHasher24::new_with_key(&self.secret);
+        hasher.write(client);
+        hasher.write(&server[..8]);
+        match client_ip {
+            IpAddr::V4(ip) => hasher.write(&ip.octets()),
+            IpAddr::V6(ip) => hasher.write(&ip.octets()),
+        }
+        LittleEndian::write_u64(&mut server[8..], hasher.finish());
+        server
+    }
+
+    /// Returns the cookie to include in a response to `client_cookie`, with a fresh server cookie.
+    pub fn response_cookie(&self, client_cookie: &Cookie, client_ip: IpAddr, now: u32) -> Cookie {
+        let server = self.server_cookie(&client_cookie.client, client_ip, now);
+        Cookie {
+            client: client_cookie.client,
+            server: Some(server.to_vec()),
+        }
+    }
+
+    /// Verifies a cookie sent by a client.
+    pub fn verify(&self, cookie: &Cookie, client_ip: IpAddr, now: u32) -> CookieStatus {
+        let server = match cookie.server {
+            None => return CookieStatus::ClientOnly,
+            Some(ref server) => server,
+        };
+        if server.len() != DNS_SERVER_COOKIE_SIZE || server[0] != DNS_SERVER_COOKIE_VERSION {
+            return CookieStatus::Invalid;
+        }
+        let timestamp = BigEndian::read_u32(&server[4..]);
+        let age = now.wrapping_sub(timestamp) as i32;
+        if age > self.max_age as i32 || age < -(self.max_clock_skew as i32) {
+            return CookieStatus::Invalid;
+        }
+        let expected = self.server_cookie(&cookie.client, client_ip, timestamp);
+        let diff = expected
+            .iter()
+            .zip(server.iter())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        if diff != 0 {
+            return CookieStatus::Invalid;
+        }
+        CookieStatus::Valid
+    }
+}
@@ -0,0 +1,216 @@
+use crate::compress::*;
+use crate::constants::*;
+use crate::rr_iterator::*;
+use byteorder::{BigEndian, ByteOrder};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// A protocol anomaly that doesn't prevent a packet from being parsed.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum DiagnosticKind {
+    /// Records from the same RRset have different TTLs.
+    InconsistentTtl,
+    /// The most significant bit of the TTL is set.
+    TtlHighBitSet,
+    /// An RRSIG record covers a type that is not present for that name in the same section.
+    RrsigWithoutCoveredType,
+    /// The OPT record advertises a payload size smaller than 512 bytes.
+    SmallEdnsPayload,
+    /// The same record appears more than once in a section.
+    DuplicateRecord,
+    /// A DNAME record is not accompanied by a synthesized CNAME record.
+    DnameWithoutCname,
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            DiagnosticKind::InconsistentTtl => "Inconsistent TTLs within an RRset",
+            DiagnosticKind::TtlHighBitSet => "TTL with the high bit set",
+            DiagnosticKind::RrsigWithoutCoveredType => "RRSIG covering a missing type",
+            DiagnosticKind::SmallEdnsPayload => "EDNS payload size smaller than 512 bytes",
+            DiagnosticKind::DuplicateRecord => "Duplicate record",
+            DiagnosticKind::DnameWithoutCname => "DNAME without a synthesized CNAME",
+        };
+        f.write_str(description)
+    }
+}
+
+/// A non-fatal anomaly found by `DNSSector::parse_with_diagnostics()`, with the record it applies to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// The anomaly.
+    pub kind: DiagnosticKind,
+    /// Offset of the record in the packet.
+    pub offset: usize,
+    /// Section of the record.
+    pub section: Section,
+    /// Index of the record within its section.
+    pub rr_index: usize,
+    /// Type of the record.
+    pub rr_type: u16,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at offset {} in section {:?}, record #{} (type {})",
+            self.kind, self.offset, self.section, self.rr_index, self.rr_type
+        )
+    }
+}
+
+/// A validated record, as seen by the collector.
+#[derive(Clone, Debug)]
+struct RecordInfo {
+    offset: usize,
+    section: Section,
+    rr_index: usize,
+    name: Vec<u8>,
+    rr_type: u16,
+    rr_class: u16,
+    rr_ttl: u32,
+    rdata: Vec<u8>,
+}
+
+impl RecordInfo {
+    fn diagnostic(&self, kind: DiagnosticKind) -> Diagnostic {
+        Diagnostic {
+            kind,
+            offset: self.offset,
+            section: self.section,
+            rr_index: self.rr_index,
+            rr_type: self.rr_type,
+        }
+    }
+
+    /// The type covered by a RRSIG record, or `0` for other types.
+    fn type_covered(&self) -> u16 {
+        if self.rr_type == Type::RRSIG.into() && self.rdata.len() >= 2 {
+            BigEndian::read_u16(&self.rdata)
+        } else {
+            0
+        }
+    }
+}
+
+/// Collects the records accepted by the parser, and looks for anomalies once the whole
+/// packet has been validated.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DiagnosticsCollector {
+    records: Vec<RecordInfo>,
+}
+
+impl DiagnosticsCollector {
+    /// Records a RR that has already been validated by the parser.
+    pub(crate) fn record(
+        &mut self,
+        packet: &[u8],
+        section: Section,
+        rr_index: usize,
+        offset: usize,
+    ) {
+        let mut name = Vec::with_capacity(DNS_MAX_HOSTNAME_LEN);
+        let name_end = Compress::copy_uncompressed_name(&mut name, packet, offset).final_offset;
+        name.make_ascii_lowercase();
+        let rr_type = BigEndian::read_u16(&packet[name_end + DNS_RR_TYPE_OFFSET..]);
+        let rr_class = BigEndian::read_u16(&packet[name_end + DNS_RR_CLASS_OFFSET..]);
+        let rr_ttl = BigEndian::read_u32(&packet[name_end + DNS_RR_TTL_OFFSET..]);
+        let rr_rdlen = BigEndian::read_u16(&packet[name_end + DNS_RR_RDLEN_OFFSET..]) as usize;
+        let raw = RRRaw {
+            packet,
+            offset,
+            name_end,
+        };
+        let mut rdata = Vec::with_capacity(DNS_RR_HEADER_SIZE + rr_rdlen);
+        Compress::uncompress_rdata(&mut rdata, raw, Some(rr_type), Some(rr_rdlen));
+        rdata.drain(..DNS_RR_HEADER_SIZE);
+        self.records.push(RecordInfo {
+            offset,
+            section,
+            rr_index,
+            name,
+            rr_type,
+            rr_class,
+            rr_ttl,
+            rdata,
+        });
+    }
+
+    /// Returns the anomalies found in the collected records, ordered by offset.
+    pub(crate) fn finish(self) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        let opt_type: u16 = Type::OPT.into();
+        let mut rrsets: HashMap<(Section, &[u8], u16, u16, u16), u32> = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut types: HashSet<(Section, &[u8], u16, u16)> = HashSet::new();
+        for record in &self.records {
+            if record.rr_type == opt_type {
+                if (record.rr_class as usize) < DNS_DEFAULT_MAX_PAYLOAD {
+                    diagnostics.push(record.diagnostic(DiagnosticKind::SmallEdnsPayload));
+                }
+                continue;
+            }
+            if record.rr_ttl & 0x8000_0000 != 0 {
+                diagnostics.push(record.diagnostic(DiagnosticKind::TtlHighBitSet));
+            }
+            let rrset = (
+                record.section,
+                &record.name[..],
+                record.rr_type,
+                record.rr_class,
+                record.type_covered(),
+            );
+            let rrset_ttl = *rrsets.entry(rrset).or_insert(record.rr_ttl);
+            if rrset_ttl != record.rr_ttl {
+                diagnostics.push(record.diagnostic(DiagnosticKind::InconsistentTtl));
+            }
+            if !seen.insert((rrset, &record.rdata[..])) {
+                diagnostics.push(record.diagnostic(DiagnosticKind::DuplicateRecord));
+            }
+            types.insert((
+                record.section,
+                &record.name[..],
+                record.rr_type,
+                record.rr_class,
+            ));
+        }
+        for record in &self.records {
+            if record.rr_type == Type::RRSIG.into() {
+                let covered = (
+                    record.section,
+                    &record.name[..],
+                    record.type_covered(),
+                    record.rr_class,
+                );
+                if !types.contains(&covered) {
+                    diagnostics.push(record.diagnostic(DiagnosticKind::RrsigWithoutCoveredType));
+                }
+            } else if record.rr_type == Type::DNAME.into() {
+                let synthesized = self.records.iter().any(|other| {
+                    other.section == record.section
+                        && other.rr_type == Type::CNAME.into()
+                        && is_subdomain(&other.name, &record.name)
+                });
+                if !synthesized {
+                    diagnostics.push(record.diagnostic(DiagnosticKind::DnameWithoutCname));
+                }
+            }
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.offset);
+        diagnostics
+    }
+}
+
+/// Returns `true` if the raw name `name` is a strict subdomain of the raw name `parent`.
+fn is_subdomain(name: &[u8], parent: &[u8]) -> bool {
+    let mut i = 0;
+    while i < name.len() && name[i] != 0 {
+        i += name[i] as usize + 1;
+        if name[i..] == *parent {
+            return true;
+        }
+    }
+    false
+}
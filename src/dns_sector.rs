@@ -1,7 +1,11 @@
 use crate::compress::*;
 use crate::constants::*;
+use crate::diagnostics::*;
 use crate::errors::*;
+use crate::parse_options::*;
 use crate::parsed_packet::*;
+use crate::parsed_packet_ref::*;
+use crate::tsig::*;
 use byteorder::{BigEndian, ByteOrder};
 use std::mem;
 
@@ -9,9 +13,12 @@ use std::mem;
 /// so that individual sections can be accessed quickly.
 ///
 /// The functions implemented here assume an untrusted input packet.
+///
+/// The packet is usually owned (`Vec<u8>`), but it can also be borrowed (`&[u8]`), in order
+/// to inspect it without any copies - See `DNSSector::from_slice()`.
 #[derive(Clone, Debug)]
-pub struct DNSSector {
-    pub packet: Vec<u8>,
+pub struct DNSSector<B = Vec<u8>> {
+    pub packet: B,
     pub offset: usize,
     pub edns_start: Option<usize>,
     pub edns_end: Option<usize>,
@@ -20,6 +27,20 @@ pub struct DNSSector {
     pub edns_version: Option<u8>,
     pub ext_flags: Option<u16>,
     pub max_payload: usize,
+    current_section: Option<Section>,
+    current_rr_index: Option<usize>,
+    current_rr_type: Option<u16>,
+    current_rr_offset: usize,
+    diagnostics: Option<DiagnosticsCollector>,
+}
+
+/// Location of the sections of a validated packet.
+struct Sections {
+    offset_question: Option<usize>,
+    offset_answers: Option<usize>,
+    offset_nameservers: Option<usize>,
+    offset_additional: Option<usize>,
+    packet_len: usize,
 }
 
 impl DNSSector {
@@ -99,10 +120,170 @@ impl DNSSector {
         BigEndian::write_u16(&mut packet[10..], value)
     }
 
+    /// Checks that an untrusted encoded DNS name is valid and does not contain any indirections.
+    /// Returns the location right after the name.
+    pub fn check_uncompressed_name(packet: &[u8], mut offset: usize) -> Result<usize, Error> {
+        let packet_len = packet.len();
+        let mut name_len = 0;
+        if offset >= packet_len {
+            bail!(DSError::InternalError("Offset outside packet boundaries"));
+        }
+        if 1 > packet_len - offset {
+            bail!(DSError::InvalidName("Empty name"));
+        }
+        loop {
+            if offset >= packet_len {
+                bail!(DSError::InvalidName("Truncated name"));
+            }
+            let label_len = match packet[offset] {
+                len if len & 0xc0 == 0xc0 => bail!(DSError::InvalidName("Unexpected compression")),
+                len if len > 0x3f => bail!(DSError::InvalidName("Label length too long")),
+                len => len as usize,
+            };
+            if label_len >= packet_len - offset {
+                bail!(DSError::InvalidName("Out-of-bounds name"));
+            }
+            name_len += label_len + 1;
+            if name_len > DNS_MAX_HOSTNAME_LEN {
+                bail!(DSError::InvalidName("Name too long"));
+            }
+            offset += label_len + 1;
+            if label_len == 0 {
+                break;
+            }
+        }
+        Ok(offset)
+    }
+
+    /// Builds a `DNSSector` structure for a given untrusted DNS packet.
+    pub fn new(packet: Vec<u8>) -> Result<Self, Error> {
+        Ok(DNSSector::with_packet(packet))
+    }
+
+    /// Parses and validates all records from all sections of an untrusted DNS packet.
+    /// If the validation succeeds, a `ParsedPacket` structure containing information
+    /// to quickly access (extended) flags and individual sections is returned.
+    pub fn parse(self) -> Result<ParsedPacket, ParseError> {
+        self.parse_with(&ParseOptions::default())
+    }
+
+    /// Parses an untrusted DNS packet, accepting packets that are well-formed but violate
+    /// the usual rules: no or multiple questions, records in queries, unusual classes or
+    /// trailing data, which is removed. See `ParseOptions::lenient()`.
+    pub fn parse_lenient(self) -> Result<ParsedPacket, ParseError> {
+        self.parse_with(&ParseOptions::lenient())
+    }
+
+    /// Parses and validates an untrusted DNS packet, like `parse()`, but using custom
+    /// validation options.
+    pub fn parse_with(mut self, options: &ParseOptions) -> Result<ParsedPacket, ParseError> {
+        let sections = self
+            .parse_sections(options)
+            .map_err(|err| self.parse_error(err))?;
+        Ok(self.into_parsed_packet(sections, options))
+    }
+
+    /// Parses and validates an untrusted DNS packet, like `parse_with()`, and also returns
+    /// a list of protocol anomalies that are not fatal.
+    pub fn parse_with_diagnostics(
+        mut self,
+        options: &ParseOptions,
+    ) -> Result<(ParsedPacket, Vec<Diagnostic>), ParseError> {
+        self.diagnostics = Some(DiagnosticsCollector::default());
+        let sections = self
+            .parse_sections(options)
+            .map_err(|err| self.parse_error(err))?;
+        let diagnostics = self
+            .diagnostics
+            .take()
+            .map(DiagnosticsCollector::finish)
+            .unwrap_or_default();
+        Ok((self.into_parsed_packet(sections, options), diagnostics))
+    }
+
+    fn into_parsed_packet(mut self, sections: Sections, options: &ParseOptions) -> ParsedPacket {
+        self.packet.truncate(sections.packet_len);
+        ParsedPacket {
+            packet: Some(self.packet),
+            offset_question: sections.offset_question,
+            offset_answers: sections.offset_answers,
+            offset_nameservers: sections.offset_nameservers,
+            offset_additional: sections.offset_additional,
+            offset_edns: self.edns_start,
+            ext_rcode: self.ext_rcode,
+            edns_version: self.edns_version,
+            ext_flags: self.ext_flags,
+            edns_count: self.edns_count,
+            maybe_compressed: true,
+            max_payload: self.max_payload,
+            max_packet_size: options.max_packet_size,
+            cached: None,
+            scratch: Vec::new(),
+        }
+    }
+}
+
+impl<'a> DNSSector<&'a [u8]> {
+    /// Builds a `DNSSector` structure for a given untrusted DNS packet, without copying it.
+    pub fn from_slice(packet: &'a [u8]) -> Self {
+        DNSSector::with_packet(packet)
+    }
+
+    /// Parses and validates a borrowed packet, like `parse()`.
+    /// The returned `ParsedPacketRef` gives read-only access to the packet, without copies.
+    pub fn parse_ref(self) -> Result<ParsedPacketRef<'a>, ParseError> {
+        self.parse_ref_with(&ParseOptions::default())
+    }
+
+    /// Parses and validates a borrowed packet, like `parse_with()`.
+    pub fn parse_ref_with(
+        mut self,
+        options: &ParseOptions,
+    ) -> Result<ParsedPacketRef<'a>, ParseError> {
+        let sections = self
+            .parse_sections(options)
+            .map_err(|err| self.parse_error(err))?;
+        Ok(ParsedPacketRef {
+            packet: &self.packet[..sections.packet_len],
+            offset_question: sections.offset_question,
+            offset_answers: sections.offset_answers,
+            offset_nameservers: sections.offset_nameservers,
+            offset_additional: sections.offset_additional,
+            offset_edns: self.edns_start,
+            edns_count: self.edns_count,
+            ext_rcode: self.ext_rcode,
+            edns_version: self.edns_version,
+            ext_flags: self.ext_flags,
+            max_payload: self.max_payload,
+        })
+    }
+}
+
+impl<B: AsRef<[u8]>> DNSSector<B> {
+    /// Creates a parser for an untrusted packet.
+    pub(crate) fn with_packet(packet: B) -> Self {
+        DNSSector {
+            packet,
+            offset: 0,
+            edns_start: None,
+            edns_end: None,
+            edns_count: 0,
+            ext_rcode: None,
+            edns_version: None,
+            ext_flags: None,
+            max_payload: DNS_DEFAULT_MAX_PAYLOAD,
+            current_section: None,
+            current_rr_index: None,
+            current_rr_type: None,
+            current_rr_offset: 0,
+            diagnostics: None,
+        }
+    }
+
     /// Returns the number of yet unparsed bytes.
     #[inline]
     fn remaining_len(&self) -> usize {
-        self.packet.len() - self.offset
+        self.packet.as_ref().len() - self.offset
     }
 
     /// Makes sure that at least `len` bytes remain to be parsed.
@@ -116,7 +297,7 @@ impl DNSSector {
 
     /// Sets the internal offset to the data to be parsed to an arbitrary location
     pub fn set_offset(&mut self, offset: usize) -> Result<usize, Error> {
-        if offset >= self.packet.len() {
+        if offset >= self.packet.as_ref().len() {
             bail!(DSError::InternalError(
                 "Setting offset past the end of the packet",
             ))
@@ -136,14 +317,14 @@ impl DNSSector {
     fn u8_load(&self, rr_offset: usize) -> Result<u8, Error> {
         self.ensure_remaining_len(rr_offset + 1)?;
         let offset = self.offset + rr_offset;
-        Ok(self.packet[offset])
+        Ok(self.packet.as_ref()[offset])
     }
 
     #[inline]
     fn be16_load(&self, rr_offset: usize) -> Result<u16, Error> {
         self.ensure_remaining_len(rr_offset + 2)?;
         let offset = self.offset + rr_offset;
-        Ok(BigEndian::read_u16(&self.packet[offset..]))
+        Ok(BigEndian::read_u16(&self.packet.as_ref()[offset..]))
     }
 
     #[allow(dead_code)]
@@ -151,14 +332,17 @@ impl DNSSector {
     fn be32_load(&self, rr_offset: usize) -> Result<u32, Error> {
         self.ensure_remaining_len(rr_offset + 4)?;
         let offset = self.offset + rr_offset;
-        Ok(BigEndian::read_u32(&self.packet[offset..]))
+        Ok(BigEndian::read_u32(&self.packet.as_ref()[offset..]))
     }
 
     /// Checks that an encoded DNS name is valid. This includes following indirections for
     /// compressed names, checks for label lengths, checks for truncated names and checks for
     /// cycles.
     fn check_compressed_name(&self, offset: usize) -> Result<usize, Error> {
-        Compress::check_compressed_name(&self.packet, offset)
+        Ok(Compress::check_compressed_name(
+            self.packet.as_ref(),
+            offset,
+        )?)
     }
 
     /// Verifies that a name has been properly encoded, and sets the internal
@@ -181,7 +365,6 @@ impl DNSSector {
     }
 
     /// Returns the TTL of the record currently being parsed.
-    #[allow(dead_code)]
     #[inline]
     fn rr_ttl(&self) -> Result<u32, Error> {
         self.be32_load(DNS_RR_TTL_OFFSET)
@@ -193,127 +376,146 @@ impl DNSSector {
         self.be16_load(DNS_RR_RDLEN_OFFSET).map(|x| x as usize)
     }
 
-    /// Ensure that the record currently being parsed has the `IN` class.
+    /// Ensure that the record currently being parsed has one of the allowed classes.
     #[inline]
-    fn ensure_in_class(&self) -> Result<(), Error> {
-        if self.rr_class()? != Class::IN.into() {
-            bail!(DSError::UnsupportedClass(self.rr_class().unwrap_or(0)));
+    fn ensure_allowed_class(&self, options: &ParseOptions) -> Result<(), Error> {
+        let rr_class = self.rr_class()?;
+        if !options.is_class_allowed(rr_class) {
+            bail!(DSError::UnsupportedClass(rr_class));
         }
         Ok(())
     }
 
-    /// Builds a `DNSSector` structure for a given untrusted DNS packet.
-    pub fn new(packet: Vec<u8>) -> Result<Self, Error> {
-        let dns_sector = DNSSector {
-            packet,
-            offset: 0,
-            edns_start: None,
-            edns_end: None,
-            edns_count: 0,
-            ext_rcode: None,
-            edns_version: None,
-            ext_flags: None,
-            max_payload: 512,
+    /// Converts an error into a `ParseError`, recording the location of the record being parsed.
+    fn parse_error(&self, err: Error) -> ParseError {
+        let kind = match err.downcast::<ParseError>() {
+            Ok(parse_error) => parse_error.kind,
+            Err(err) => match err.downcast::<DSError>() {
+                Ok(kind) => kind,
+                Err(err) => DSError::Other(format!("{:#}", err)),
+            },
         };
-        Ok(dns_sector)
+        ParseError {
+            kind,
+            offset: self.current_rr_offset,
+            section: self.current_section,
+            rr_index: self.current_rr_index,
+            rr_type: self.current_rr_type,
+        }
     }
 
-    /// Parses and validates all records from all sections of an untrusted DNS packet.
-    /// If the validation succeeds, a `ParsedPacket` structure containing information
-    /// to quickly access (extended) flags and individual sections is returned.
-    pub fn parse(mut self) -> Result<ParsedPacket, Error> {
-        let packet_len = self.packet.len();
-        if packet_len < DNS_HEADER_SIZE {
+    /// Records the location of the record about to be parsed, for error reporting.
+    fn set_current_rr(&mut self, section: Option<Section>, rr_index: Option<usize>) {
+        self.current_section = section;
+        self.current_rr_index = rr_index;
+        self.current_rr_type = None;
+        self.current_rr_offset = self.offset;
+    }
+
+    /// Validates all the sections of the packet, and returns their location.
+    fn parse_sections(&mut self, options: &ParseOptions) -> Result<Sections, Error> {
+        let packet = self.packet.as_ref();
+        if packet.len() < DNS_HEADER_SIZE {
             bail!(DSError::PacketTooSmall)
         }
-        let is_response = Self::is_response(&self.packet);
-        let qdcount = Self::qdcount(&self.packet);
-        if qdcount == 0 {
+        let is_response = DNSSector::is_response(packet);
+        let qdcount = DNSSector::qdcount(packet);
+        let ancount = DNSSector::ancount(packet);
+        let nscount = DNSSector::nscount(packet);
+        let arcount = DNSSector::arcount(packet);
+        if let Some(max_rr_count) = options.max_rr_count {
+            let rr_count =
+                qdcount as usize + ancount as usize + nscount as usize + arcount as usize;
+            if rr_count > max_rr_count {
+                bail!(DSError::InvalidPacket("Too many records"));
+            }
+        }
+        if qdcount == 0 && !options.allow_no_question {
             bail!(DSError::InvalidPacket(
                 "A DNS packet should contain a question",
             ));
         }
-        if qdcount > 1 {
+        if qdcount > 1 && !options.allow_multiple_questions {
             bail!(DSError::InvalidPacket(
                 "A DNS packet cannot contain more than one question",
             ));
         }
-        self.set_offset(DNS_QUESTION_OFFSET)?;
+        self.offset = DNS_QUESTION_OFFSET;
         let offset_question = if qdcount > 0 { Some(self.offset) } else { None };
-        if qdcount != 0 {
-            assert_eq!(qdcount, 1);
-            self.parse_question()?;
+        for i in 0..qdcount as usize {
+            self.set_current_rr(Some(Section::Question), Some(i));
+            self.parse_question(options)?;
         }
-        let ancount = Self::ancount(&self.packet);
-        if !is_response && ancount > 0 {
+        self.set_current_rr(Some(Section::Answer), None);
+        if !is_response && ancount > 0 && !options.allow_records_in_queries {
             bail!(DSError::InvalidPacket(
                 "A question shouldn't also contain answers"
             ));
         }
         let offset_answers = if ancount > 0 { Some(self.offset) } else { None };
-        for _ in 0..ancount {
-            self.parse_rr(Section::Answer)?;
+        for i in 0..ancount as usize {
+            self.parse_section_rr(Section::Answer, i, options)?;
         }
-        let nscount = Self::nscount(&self.packet);
-        if !is_response && nscount > 0 {
+        self.set_current_rr(Some(Section::NameServers), None);
+        if !is_response && nscount > 0 && !options.allow_records_in_queries {
             bail!(DSError::InvalidPacket(
                 "A question shouldn't also contain name servers"
             ));
         }
         let offset_nameservers = if nscount > 0 { Some(self.offset) } else { None };
-        for _ in 0..nscount {
-            self.parse_rr(Section::NameServers)?;
+        for i in 0..nscount as usize {
+            self.parse_section_rr(Section::NameServers, i, options)?;
         }
-        let arcount = Self::arcount(&self.packet);
         let offset_additional = if arcount > 0 { Some(self.offset) } else { None };
-        for _ in 0..arcount {
-            self.parse_rr(Section::Additional)?;
+        for i in 0..arcount as usize {
+            self.parse_section_rr(Section::Additional, i, options)?;
         }
-        if self.remaining_len() > 0 {
+        self.set_current_rr(None, None);
+        if self.remaining_len() > 0 && !options.allow_trailing_data {
             bail!(DSError::InvalidPacket(
                 "Extra data found after the last record",
             ));
         }
-        let edns_start = self.edns_start;
-        let ext_rcode = self.ext_rcode;
-        let edns_version = self.edns_version;
-        let ext_flags = self.ext_flags;
-        let edns_count = self.edns_count;
-        let max_payload = self.max_payload;
-        let parsed_packet = ParsedPacket {
-            packet: Some(self.packet),
+        Ok(Sections {
             offset_question,
             offset_answers,
             offset_nameservers,
             offset_additional,
-            offset_edns: edns_start,
-            ext_rcode,
-            edns_version,
-            ext_flags,
-            edns_count,
-            maybe_compressed: true,
-            max_payload,
-            cached: None,
-        };
-        Ok(parsed_packet)
+            packet_len: self.offset,
+        })
     }
 
     /// Parses a question RR.
-    fn parse_question(&mut self) -> Result<(), Error> {
+    fn parse_question(&mut self, options: &ParseOptions) -> Result<(), Error> {
         self.skip_name()?;
-        self.ensure_in_class()?;
-        if self.rr_class()? != Class::IN.into() {
-            bail!(DSError::UnsupportedClass(self.rr_class().unwrap_or(0)));
-        }
+        self.current_rr_type = Some(self.rr_type()?);
+        self.ensure_allowed_class(options)?;
         self.increment_offset(DNS_RR_QUESTION_HEADER_SIZE)?;
         Ok(())
     }
 
+    /// Parses the record `rr_index` of a section, and collects it for diagnostics if required.
+    fn parse_section_rr(
+        &mut self,
+        section: Section,
+        rr_index: usize,
+        options: &ParseOptions,
+    ) -> Result<(), Error> {
+        self.set_current_rr(Some(section), Some(rr_index));
+        let offset = self.offset;
+        self.parse_rr(section, options)?;
+        if let Some(diagnostics) = self.diagnostics.as_mut() {
+            diagnostics.record(self.packet.as_ref(), section, rr_index, offset);
+        }
+        Ok(())
+    }
+
     /// Parses a RR from the answer, nameservers or additional sections.
-    fn parse_rr(&mut self, section: Section) -> Result<(), Error> {
+    fn parse_rr(&mut self, section: Section, options: &ParseOptions) -> Result<(), Error> {
         let rr_start_offset = self.offset;
         self.skip_name()?;
         let rr_type = self.rr_type()?;
+        self.current_rr_type = Some(rr_type);
         let rr_rdlen = self.rr_rdlen()?;
         match rr_type {
             x if x == Type::OPT.into() => {
@@ -329,12 +531,33 @@ impl DNSSector {
                 }
                 return self.parse_opt();
             }
+            x if x == Type::TSIG.into() => {
+                let arcount = DNSSector::arcount(self.packet.as_ref()) as usize;
+                if section != Section::Additional || self.current_rr_index != Some(arcount - 1) {
+                    bail!(DSError::InvalidPacket(
+                        "TSIG RRs must be the last record of the additional section"
+                    ));
+                }
+                if self.rr_class()? != Class::ANY.into() {
+                    bail!(DSError::InvalidPacket("TSIG RRs must have the ANY class"));
+                }
+                if self.rr_ttl()? != 0 {
+                    bail!(DSError::InvalidPacket("TSIG RRs must have a null TTL"));
+                }
+                self.ensure_remaining_len(DNS_RR_HEADER_SIZE + rr_rdlen)?;
+                let rdata_offset = self.offset + DNS_RR_HEADER_SIZE;
+                TsigRecord::check_rdata(
+                    &self.packet.as_ref()[rdata_offset..rdata_offset + rr_rdlen],
+                )?;
+                self.increment_offset(DNS_RR_HEADER_SIZE + rr_rdlen)?;
+            }
             x if x == Type::NS.into() || x == Type::CNAME.into() || x == Type::PTR.into() => {
                 if rr_rdlen == 0 {
                     bail!(DSError::PacketTooSmall);
                 }
                 self.increment_offset(DNS_RR_HEADER_SIZE)?;
-                let final_offset = Compress::check_compressed_name(&self.packet, self.offset)?;
+                let final_offset =
+                    Compress::check_compressed_name(self.packet.as_ref(), self.offset)?;
                 if final_offset - self.offset != rr_rdlen {
                     bail!(DSError::InvalidPacket(
                         "Unexpected data after name in rdata",
@@ -347,7 +570,8 @@ impl DNSSector {
                     bail!(DSError::PacketTooSmall);
                 }
                 self.increment_offset(DNS_RR_HEADER_SIZE)?;
-                let final_offset = Compress::check_compressed_name(&self.packet, self.offset + 2)?;
+                let final_offset =
+                    Compress::check_compressed_name(self.packet.as_ref(), self.offset + 2)?;
                 if final_offset - self.offset != rr_rdlen {
                     bail!(DSError::InvalidPacket(
                         "Unexpected data after name in MX rdata",
@@ -360,8 +584,10 @@ impl DNSSector {
                     bail!(DSError::PacketTooSmall);
                 }
                 self.increment_offset(DNS_RR_HEADER_SIZE)?;
-                let final_offset_1 = Compress::check_compressed_name(&self.packet, self.offset)?;
-                let final_offset_2 = Compress::check_compressed_name(&self.packet, final_offset_1)?;
+                let final_offset_1 =
+                    Compress::check_compressed_name(self.packet.as_ref(), self.offset)?;
+                let final_offset_2 =
+                    Compress::check_compressed_name(self.packet.as_ref(), final_offset_1)?;
                 if final_offset_2 - self.offset != rr_rdlen - 20 {
                     bail!(DSError::InvalidPacket(
                         "Unexpected data after name in SOA rdata",
@@ -374,7 +600,8 @@ impl DNSSector {
                     bail!(DSError::PacketTooSmall);
                 }
                 self.increment_offset(DNS_RR_HEADER_SIZE)?;
-                let final_offset = Self::check_uncompressed_name(&self.packet, self.offset)?;
+                let final_offset =
+                    DNSSector::check_uncompressed_name(self.packet.as_ref(), self.offset)?;
                 if final_offset - self.offset != rr_rdlen {
                     bail!(DSError::InvalidPacket(
                         "Unexpected data after name in DNAME rdata",
@@ -382,6 +609,76 @@ impl DNSSector {
                 }
                 self.increment_offset(rr_rdlen)?;
             }
+            x if x == Type::MINFO.into() => {
+                let rdata_end = self.check_rdata_names(rr_rdlen, 0, 2, true)?;
+                self.check_rdata_end(rdata_end, rr_rdlen)?;
+            }
+            x if x == Type::KX.into() => {
+                let rdata_end = self.check_rdata_names(rr_rdlen, 2, 1, false)?;
+                self.check_rdata_end(rdata_end, rr_rdlen)?;
+            }
+            x if x == Type::SIG.into() || x == Type::NXT.into() => {
+                let (prefix_len, names) = Compress::rfc3597_rdata_names(x).unwrap();
+                let compressed = options.allow_compressed_rdata_names;
+                self.check_rdata_names(rr_rdlen, prefix_len, names, compressed)?;
+                self.increment_offset(DNS_RR_HEADER_SIZE + rr_rdlen)?;
+            }
+            x if Compress::rfc3597_rdata_names(x).is_some() => {
+                let (prefix_len, names) = Compress::rfc3597_rdata_names(x).unwrap();
+                let compressed = options.allow_compressed_rdata_names;
+                let rdata_end = self.check_rdata_names(rr_rdlen, prefix_len, names, compressed)?;
+                self.check_rdata_end(rdata_end, rr_rdlen)?;
+            }
+            x if x == Type::NAPTR.into() => {
+                self.ensure_remaining_len(DNS_RR_HEADER_SIZE + rr_rdlen)?;
+                let rdata_offset = self.offset + DNS_RR_HEADER_SIZE;
+                let rdata_end = rdata_offset + rr_rdlen;
+                let packet = self.packet.as_ref();
+                let mut offset = rdata_offset + 4;
+                for _ in 0..3 {
+                    if offset >= rdata_end {
+                        bail!(DSError::PacketTooSmall);
+                    }
+                    offset += 1 + packet[offset] as usize;
+                }
+                if offset >= rdata_end {
+                    bail!(DSError::PacketTooSmall);
+                }
+                let final_offset = if options.allow_compressed_rdata_names {
+                    Compress::check_compressed_name(packet, offset)?
+                } else {
+                    DNSSector::check_uncompressed_name(packet, offset)?
+                };
+                if final_offset != rdata_end {
+                    bail!(DSError::InvalidPacket(
+                        "Unexpected data after name in NAPTR rdata",
+                    ))
+                }
+                self.increment_offset(DNS_RR_HEADER_SIZE + rr_rdlen)?;
+            }
+            x if x == Type::RRSIG.into() => {
+                self.check_rdata_names(rr_rdlen, 18, 1, false)?;
+                self.increment_offset(DNS_RR_HEADER_SIZE + rr_rdlen)?;
+            }
+            x if x == Type::NSEC.into() => {
+                let mut offset = self.check_rdata_names(rr_rdlen, 0, 1, false)?;
+                let rdata_end = self.offset + DNS_RR_HEADER_SIZE + rr_rdlen;
+                let packet = self.packet.as_ref();
+                while offset < rdata_end {
+                    if rdata_end - offset < 2 {
+                        bail!(DSError::InvalidPacket("Truncated NSEC type bitmap"));
+                    }
+                    let bitmap_len = packet[offset + 1] as usize;
+                    if bitmap_len == 0 || bitmap_len > 32 {
+                        bail!(DSError::InvalidPacket("Invalid NSEC type bitmap length"));
+                    }
+                    offset += 2 + bitmap_len;
+                }
+                if offset != rdata_end {
+                    bail!(DSError::InvalidPacket("Truncated NSEC type bitmap"));
+                }
+                self.increment_offset(DNS_RR_HEADER_SIZE + rr_rdlen)?;
+            }
             x if x == Type::A.into() => {
                 if rr_rdlen != 4 {
                     bail!(DSError::InvalidPacket(
@@ -405,6 +702,50 @@ impl DNSSector {
         Ok(())
     }
 
+    /// Checks the names of a record whose data starts with `prefix_len` bytes of fixed-size
+    /// fields, followed by `names` names that must all fit within the record data.
+    /// Returns the offset right after the last name.
+    fn check_rdata_names(
+        &self,
+        rr_rdlen: usize,
+        prefix_len: usize,
+        names: usize,
+        compressed: bool,
+    ) -> Result<usize, Error> {
+        self.ensure_remaining_len(DNS_RR_HEADER_SIZE + rr_rdlen)?;
+        if rr_rdlen <= prefix_len {
+            bail!(DSError::PacketTooSmall);
+        }
+        let packet = self.packet.as_ref();
+        let rdata_end = self.offset + DNS_RR_HEADER_SIZE + rr_rdlen;
+        let mut offset = self.offset + DNS_RR_HEADER_SIZE + prefix_len;
+        for _ in 0..names {
+            if offset >= rdata_end {
+                bail!(DSError::PacketTooSmall);
+            }
+            offset = if compressed {
+                Compress::check_compressed_name(packet, offset)?
+            } else {
+                DNSSector::check_uncompressed_name(packet, offset)?
+            };
+            if offset > rdata_end {
+                bail!(DSError::InvalidPacket("Name extends past the end of rdata"));
+            }
+        }
+        Ok(offset)
+    }
+
+    /// Makes sure that the record data ends at `final_offset`, and skips the record.
+    fn check_rdata_end(&mut self, final_offset: usize, rr_rdlen: usize) -> Result<(), Error> {
+        if final_offset != self.offset + DNS_RR_HEADER_SIZE + rr_rdlen {
+            bail!(DSError::InvalidPacket(
+                "Unexpected data after name in rdata",
+            ))
+        }
+        self.increment_offset(DNS_RR_HEADER_SIZE + rr_rdlen)?;
+        Ok(())
+    }
+
     /// Returns the number of unparsed bytes from the edns pseudo-section.
     #[inline]
     fn edns_remaining_len(&self) -> usize {
@@ -435,7 +776,8 @@ impl DNSSector {
     fn edns_be16_load(&self, rr_offset: usize) -> Result<u16, Error> {
         self.edns_ensure_remaining_len(rr_offset + 2)?;
         let offset = self.offset + rr_offset;
-        Ok((self.packet[offset] as u16) << 8 | self.packet[offset + 1] as u16)
+        let packet = self.packet.as_ref();
+        Ok((packet[offset] as u16) << 8 | packet[offset + 1] as u16)
     }
 
     #[allow(dead_code)]
@@ -443,10 +785,11 @@ impl DNSSector {
     fn edns_be32_load(&self, rr_offset: usize) -> Result<u32, Error> {
         self.edns_ensure_remaining_len(rr_offset + 4)?;
         let offset = self.offset + rr_offset;
-        Ok((self.packet[offset] as u32) << 24
-            | (self.packet[offset + 1] as u32) << 16
-            | (self.packet[offset + 2] as u32) << 8
-            | self.packet[offset + 3] as u32)
+        let packet = self.packet.as_ref();
+        Ok((packet[offset] as u32) << 24
+            | (packet[offset + 1] as u32) << 16
+            | (packet[offset + 2] as u32) << 8
+            | packet[offset + 3] as u32)
     }
 
     /// Returns the extended code of a record within the edns pseudo-section.
@@ -524,39 +867,4 @@ impl DNSSector {
         debug_assert_eq!(self.edns_remaining_len(), 0);
         Ok(())
     }
-
-    /// Checks that an untrusted encoded DNS name is valid and does not contain any indirections.
-    /// Returns the location right after the name.
-    pub fn check_uncompressed_name(packet: &[u8], mut offset: usize) -> Result<usize, Error> {
-        let packet_len = packet.len();
-        let mut name_len = 0;
-        if offset >= packet_len {
-            bail!(DSError::InternalError("Offset outside packet boundaries"));
-        }
-        if 1 > packet_len - offset {
-            bail!(DSError::InvalidName("Empty name"));
-        }
-        loop {
-            if offset >= packet_len {
-                bail!(DSError::InvalidName("Truncated name"));
-            }
-            let label_len = match packet[offset] {
-                len if len & 0xc0 == 0xc0 => bail!(DSError::InvalidName("Unexpected compression")),
-                len if len > 0x3f => bail!(DSError::InvalidName("Label length too long")),
-                len => len as usize,
-            };
-            if label_len >= packet_len - offset {
-                bail!(DSError::InvalidName("Out-of-bounds name"));
-            }
-            name_len += label_len + 1;
-            if name_len > DNS_MAX_HOSTNAME_LEN {
-                bail!(DSError::InvalidName("Name too long"));
-            }
-            offset += label_len + 1;
-            if label_len == 0 {
-                break;
-            }
-        }
-        Ok(offset)
-    }
 }
@@ -1,6 +1,7 @@
 use crate::constants::*;
 use crate::parsed_packet::*;
 use crate::rr_iterator::*;
+use byteorder::{BigEndian, ByteOrder};
 
 #[derive(Debug)]
 pub struct EdnsIterator<'t> {
@@ -68,31 +69,34 @@ impl<'t> DNSIterable for EdnsIterator<'t> {
         &mut self.rr_iterator.parsed_packet
     }
 
-    fn next(mut self) -> Option<Self> {
-        {
-            let rr_iterator = &mut self.rr_iterator;
-            let parsed_packet = &mut rr_iterator.parsed_packet;
-            debug_assert_eq!(rr_iterator.section, Section::Edns);
-            if rr_iterator.offset.is_none() {
-                let count = parsed_packet.edns_count;
-                let offset = parsed_packet.offset_edns;
-                if count == 0 {
-                    return None;
-                }
-                rr_iterator.rrs_left = count;
-                rr_iterator.offset_next = offset.unwrap();
-            }
-            if rr_iterator.rrs_left == 0 {
-                return None;
+    fn advance(&mut self) -> bool {
+        let rr_iterator = &mut self.rr_iterator;
+        let parsed_packet = &mut rr_iterator.parsed_packet;
+        debug_assert_eq!(rr_iterator.section, Section::Edns);
+        if rr_iterator.exhausted {
+            return false;
+        }
+        if rr_iterator.offset.is_none() {
+            let count = parsed_packet.edns_count;
+            let offset = parsed_packet.offset_edns;
+            if count == 0 {
+                rr_iterator.exhausted = true;
+                return false;
             }
-            rr_iterator.rrs_left -= 1;
-            rr_iterator.offset = Some(rr_iterator.offset_next);
-            rr_iterator.name_end = rr_iterator.offset.unwrap();
-            let offset_next =
-                RRIterator::edns_skip_rr(&parsed_packet.packet(), rr_iterator.name_end);
-            rr_iterator.offset_next = offset_next;
+            rr_iterator.rrs_left = count;
+            rr_iterator.offset_next = offset.unwrap();
+        }
+        if rr_iterator.rrs_left == 0 {
+            rr_iterator.offset = None;
+            rr_iterator.exhausted = true;
+            return false;
         }
-        Some(self)
+        rr_iterator.rrs_left -= 1;
+        rr_iterator.offset = Some(rr_iterator.offset_next);
+        rr_iterator.name_end = rr_iterator.offset.unwrap();
+        let offset_next = RRIterator::edns_skip_rr(&parsed_packet.packet(), rr_iterator.name_end);
+        rr_iterator.offset_next = offset_next;
+        true
     }
 }
 
@@ -100,4 +104,20 @@ impl<'t> EdnsIterator<'t> {
     pub fn new(rr_iterator: RRIterator<'t>) -> Self {
         EdnsIterator { rr_iterator }
     }
+
+    /// Returns the code of the current edns option.
+    #[inline]
+    pub fn option_code(&self) -> u16 {
+        let raw = self.raw();
+        BigEndian::read_u16(&raw.packet[raw.offset + DNS_EDNS_RR_CODE_OFFSET..])
+    }
+
+    /// Returns the data of the current edns option.
+    #[inline]
+    pub fn option_data(&self) -> &[u8] {
+        let raw = self.raw();
+        let offset = raw.offset + DNS_EDNS_RR_HEADER_SIZE;
+        let len = BigEndian::read_u16(&raw.packet[raw.offset + DNS_EDNS_RR_RDLEN_OFFSET..]);
+        &raw.packet[offset..offset + len as usize]
+    }
 }
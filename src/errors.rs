@@ -1,6 +1,8 @@
+use crate::constants::*;
 pub use anyhow::{anyhow, bail, ensure, Error};
+use std::fmt;
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
 pub enum DSError {
     #[error("Packet too small")]
     PacketTooSmall,
@@ -26,4 +28,66 @@ pub enum DSError {
     WrongAddressFamily,
     #[error("Parse error")]
     ParseError,
+    #[error("{0}")]
+    Other(String),
+}
+
+/// A validation error returned by the parser, with the location where the packet was rejected.
+/// Wrapped in an `Error`, it has to be downcast to a `ParseError`, not to a `DSError`:
+/// `DSError::from()` and `source()` return the underlying `kind`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    /// The reason why the packet was rejected.
+    pub kind: DSError,
+    /// Offset of the start of the record being validated. For errors that are not related
+    /// to a record, this is the offset of the header (`0`), of the section that shouldn't be
+    /// present, or of the unexpected data after the last record.
+    pub offset: usize,
+    /// Section of the record being validated, if the error is related to a section.
+    pub section: Option<Section>,
+    /// Index of the record within its section, if the error is related to a record.
+    pub rr_index: Option<usize>,
+    /// Type of the record being validated, if it could be read.
+    pub rr_type: Option<u16>,
+}
+
+impl ParseError {
+    /// Creates an error that is not associated with a specific record.
+    pub fn new(kind: DSError, offset: usize) -> Self {
+        ParseError {
+            kind,
+            offset,
+            section: None,
+            rr_index: None,
+            rr_type: None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.kind, self.offset)?;
+        if let Some(section) = self.section {
+            write!(f, " in section {:?}", section)?;
+        }
+        if let Some(rr_index) = self.rr_index {
+            write!(f, ", record #{}", rr_index)?;
+        }
+        if let Some(rr_type) = self.rr_type {
+            write!(f, " (type {})", rr_type)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.kind)
+    }
+}
+
+impl From<ParseError> for DSError {
+    fn from(parse_error: ParseError) -> Self {
+        parse_error.kind
+    }
 }
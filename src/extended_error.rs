@@ -0,0 +1,48 @@
+use crate::constants::*;
+use crate::errors::*;
+use byteorder::{BigEndian, ByteOrder};
+
+/// An extended DNS error option (RFC 8914): an info code, and an optional text
+/// intended for humans.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExtendedErrorInfo {
+    pub info_code: u16,
+    pub extra_text: String,
+}
+
+impl ExtendedErrorInfo {
+    /// Creates an extended error, with an optional text (that can be empty).
+    pub fn new(error: ExtendedError, extra_text: &str) -> Self {
+        ExtendedErrorInfo {
+            info_code: error.into(),
+            extra_text: extra_text.to_owned(),
+        }
+    }
+
+    /// Returns the error, or `None` if the info code is not a known code.
+    pub fn error(&self) -> Option<ExtendedError> {
+        ExtendedError::from_u16(self.info_code)
+    }
+
+    /// Decodes the data of an untrusted extended error option.
+    /// Invalid UTF-8 sequences in the text are replaced.
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < DNS_EDE_INFO_CODE_SIZE {
+            bail!(DSError::InvalidPacket("Extended error option too short"));
+        }
+        let info_code = BigEndian::read_u16(data);
+        let extra_text = String::from_utf8_lossy(&data[DNS_EDE_INFO_CODE_SIZE..]).into_owned();
+        Ok(ExtendedErrorInfo {
+            info_code,
+            extra_text,
+        })
+    }
+
+    /// Encodes the extended error as the data of an edns option.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = vec![0u8; DNS_EDE_INFO_CODE_SIZE];
+        BigEndian::write_u16(&mut data, self.info_code);
+        data.extend_from_slice(self.extra_text.as_bytes());
+        data
+    }
+}
@@ -18,27 +18,47 @@
 extern crate chomp;
 
 pub mod c_abi;
+pub mod client_subnet;
 pub mod compress;
 pub mod constants;
+pub mod cookie;
+pub mod diagnostics;
 pub mod dns_sector;
 pub mod edns_iterator;
 pub mod errors;
+pub mod extended_error;
+pub mod padding;
+pub mod parse_options;
 pub mod parsed_packet;
+pub mod parsed_packet_ref;
 pub mod question_iterator;
+pub mod rdata;
 pub mod renamer;
 pub mod response_iterator;
 pub mod rr_iterator;
 pub mod synth;
+pub mod tcp;
+pub mod tsig;
 
 pub use crate::c_abi::*;
+pub use crate::client_subnet::*;
 pub use crate::compress::*;
 pub use crate::constants::*;
+pub use crate::cookie::*;
+pub use crate::diagnostics::*;
 pub use crate::dns_sector::*;
 pub use crate::edns_iterator::*;
 pub use crate::errors::*;
+pub use crate::extended_error::*;
+pub use crate::padding::*;
+pub use crate::parse_options::*;
 pub use crate::parsed_packet::*;
+pub use crate::parsed_packet_ref::*;
 pub use crate::question_iterator::*;
+pub use crate::rdata::*;
 pub use crate::renamer::*;
 pub use crate::response_iterator::*;
 pub use crate::rr_iterator::*;
 pub use crate::synth::*;
+pub use crate::tcp::*;
+pub use crate::tsig::*;
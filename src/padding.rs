@@ -0,0 +1,27 @@
+use crate::constants::*;
+use rand::prelude::*;
+
+/// Padding strategies for the edns padding option (RFC 7830), as described in RFC 8467.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PaddingPolicy {
+    /// Pads queries to a multiple of 128 bytes, and responses to a multiple of 468 bytes.
+    BlockLength,
+    /// Pads packets to a multiple of the given number of bytes.
+    CustomBlockLength(usize),
+    /// Adds a random number of padding bytes, up to the given maximum.
+    RandomLength(usize),
+}
+
+impl PaddingPolicy {
+    /// Returns the number of padding bytes to add to a packet whose size is `len`,
+    /// including the header of the padding option.
+    pub fn padding_len(self, len: usize, is_response: bool) -> usize {
+        let block_size = match self {
+            PaddingPolicy::BlockLength if is_response => DNS_PADDING_RESPONSE_BLOCK_SIZE,
+            PaddingPolicy::BlockLength => DNS_PADDING_QUERY_BLOCK_SIZE,
+            PaddingPolicy::CustomBlockLength(block_size) => block_size.max(1),
+            PaddingPolicy::RandomLength(max_len) => return thread_rng().gen_range(0..=max_len),
+        };
+        (block_size - len % block_size) % block_size
+    }
+}
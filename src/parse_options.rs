@@ -0,0 +1,147 @@
+use crate::constants::*;
+use std::cmp;
+
+/// Options controlling how strictly `DNSSector::parse_with()` validates untrusted packets.
+/// The default options are the ones used by `DNSSector::parse()`.
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    /// Classes accepted in the question section, as a set of `class_bit()` values.
+    allowed_classes: u8,
+    /// Accept (and discard) extra data found after the last record.
+    pub allow_trailing_data: bool,
+    /// Accept packets without a question.
+    pub allow_no_question: bool,
+    /// Accept packets with more than one question.
+    pub allow_multiple_questions: bool,
+    /// Accept answer and name server records in queries.
+    pub allow_records_in_queries: bool,
+    /// Maximum number of records, including questions, a packet can contain.
+    pub max_rr_count: Option<usize>,
+    /// Maximum size the parsed packet can grow to when it is modified.
+    pub max_packet_size: usize,
+    /// Accept compressed names in `RP`, `AFSDB`, `RT`, `SIG`, `PX`, `NXT`, `NAPTR` and `SRV` records.
+    pub allow_compressed_rdata_names: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            allowed_classes: class_bit(Class::IN),
+            allow_trailing_data: false,
+            allow_no_question: false,
+            allow_multiple_questions: false,
+            allow_records_in_queries: false,
+            max_rr_count: None,
+            max_packet_size: DNS_MAX_UNCOMPRESSED_SIZE,
+            allow_compressed_rdata_names: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        ParseOptions::default()
+    }
+
+    /// Returns options accepting anything that can be safely parsed, for dissecting
+    /// real-world traffic. These are the options used by `DNSSector::parse_lenient()`.
+    pub fn lenient() -> Self {
+        ParseOptions::default()
+            .allowed_classes(&[Class::IN, Class::CH, Class::HS, Class::NONE, Class::ANY])
+            .allow_trailing_data(true)
+            .allow_no_question(true)
+            .allow_multiple_questions(true)
+            .allow_records_in_queries(true)
+            .allow_compressed_rdata_names(true)
+    }
+
+    /// Accepts an additional class in the question section.
+    pub fn allow_class(mut self, class: Class) -> Self {
+        self.allowed_classes |= class_bit(class);
+        self
+    }
+
+    /// Sets the list of classes accepted in the question section.
+    pub fn allowed_classes(mut self, classes: &[Class]) -> Self {
+        self.allowed_classes = classes
+            .iter()
+            .fold(0, |bits, &class| bits | class_bit(class));
+        self
+    }
+
+    /// Accepts extra data after the last record. That data is removed from the parsed packet.
+    pub fn allow_trailing_data(mut self, allow: bool) -> Self {
+        self.allow_trailing_data = allow;
+        self
+    }
+
+    /// Accepts packets without a question.
+    pub fn allow_no_question(mut self, allow: bool) -> Self {
+        self.allow_no_question = allow;
+        self
+    }
+
+    /// Accepts packets with more than one question.
+    pub fn allow_multiple_questions(mut self, allow: bool) -> Self {
+        self.allow_multiple_questions = allow;
+        self
+    }
+
+    /// Accepts answer and name server records in queries.
+    pub fn allow_records_in_queries(mut self, allow: bool) -> Self {
+        self.allow_records_in_queries = allow;
+        self
+    }
+
+    /// Accepts compressed names in `RP`, `AFSDB`, `RT`, `SIG`, `PX`, `NXT`, `NAPTR` and `SRV` records.
+    /// Senders must not compress these names, but receivers should decompress them
+    /// (RFC 3597 section 4). They are decompressed along with the rest of the packet.
+    pub fn allow_compressed_rdata_names(mut self, allow: bool) -> Self {
+        self.allow_compressed_rdata_names = allow;
+        self
+    }
+
+    /// Rejects packets containing more than `max_rr_count` records, including questions.
+    pub fn max_rr_count(mut self, max_rr_count: Option<usize>) -> Self {
+        self.max_rr_count = max_rr_count;
+        self
+    }
+
+    /// Sets the maximum size the parsed packet can grow to when it is modified.
+    /// This doesn't limit the size of the packets that can be parsed.
+    /// The size cannot exceed `DNS_MAX_TCP_SIZE`.
+    pub fn max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = cmp::min(max_packet_size, DNS_MAX_TCP_SIZE);
+        self
+    }
+
+    /// Options used to recompute the structure of a packet that has already been validated.
+    pub(crate) fn internal() -> Self {
+        ParseOptions::lenient()
+    }
+
+    /// Returns `true` if `class` is accepted in the question section.
+    pub(crate) fn is_class_allowed(&self, class: u16) -> bool {
+        let class = match class {
+            x if x == Class::IN.into() => Class::IN,
+            x if x == Class::CH.into() => Class::CH,
+            x if x == Class::HS.into() => Class::HS,
+            x if x == Class::NONE.into() => Class::NONE,
+            x if x == Class::ANY.into() => Class::ANY,
+            _ => return false,
+        };
+        self.allowed_classes & class_bit(class) != 0
+    }
+}
+
+/// Returns the bit representing `class` in a set of allowed classes.
+fn class_bit(class: Class) -> u8 {
+    match class {
+        Class::IN => 1 << 0,
+        Class::CH => 1 << 1,
+        Class::HS => 1 << 2,
+        Class::NONE => 1 << 3,
+        Class::ANY => 1 << 4,
+    }
+}
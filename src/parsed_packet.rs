@@ -1,15 +1,26 @@
+use crate::client_subnet::*;
 use crate::compress::*;
 use crate::constants::*;
+use crate::cookie::*;
 use crate::dns_sector::*;
 use crate::edns_iterator::*;
 use crate::errors::*;
+use crate::extended_error::*;
+use crate::padding::*;
+use crate::parse_options::*;
+use crate::parsed_packet_ref::*;
 use crate::question_iterator::*;
 use crate::renamer::*;
 use crate::response_iterator::*;
 use crate::rr_iterator::*;
 use crate::synth::gen;
+use crate::tsig::*;
 use byteorder::{BigEndian, ByteOrder};
 use rand::prelude::*;
+use std::cmp;
+use std::io::Write;
+use std::mem;
+use std::net::IpAddr;
 
 /// A `ParsedPacket` structure contains information about a successfully parsed
 /// DNS packet, that allows quick access to (extended) flags and to individual sections.
@@ -27,7 +38,10 @@ pub struct ParsedPacket {
     pub ext_flags: Option<u16>,
     pub maybe_compressed: bool,
     pub max_payload: usize,
+    pub max_packet_size: usize,
     pub cached: Option<(Vec<u8>, u16, u16)>,
+    /// Storage reused by `compress()` and `uncompress()`
+    pub(crate) scratch: Vec<u8>,
 }
 
 impl ParsedPacket {
@@ -46,7 +60,9 @@ impl ParsedPacket {
             ext_flags: None,
             maybe_compressed: false,
             max_payload: DNS_MAX_UNCOMPRESSED_SIZE,
+            max_packet_size: DNS_MAX_UNCOMPRESSED_SIZE,
             cached: None,
+            scratch: Vec::new(),
         };
         let mut rng = thread_rng();
         let tid: u16 = rng.gen();
@@ -62,6 +78,27 @@ impl ParsedPacket {
         self.packet.unwrap()
     }
 
+    /// Parses a new packet, reusing the storage of the current one.
+    /// The current packet is left untouched if the new one is not valid.
+    pub fn reset_with(&mut self, packet: &[u8]) -> Result<(), ParseError> {
+        self.reset_with_options(packet, &ParseOptions::new())
+    }
+
+    /// Parses a new packet using custom parsing options, reusing the storage of the current one.
+    /// The current packet is left untouched if the new one is not valid.
+    pub fn reset_with_options(
+        &mut self,
+        packet: &[u8],
+        options: &ParseOptions,
+    ) -> Result<(), ParseError> {
+        let parsed_packet_ref = DNSSector::from_slice(packet).parse_ref_with(options)?;
+        let buffer = self.packet.take().unwrap_or_default();
+        let scratch = mem::take(&mut self.scratch);
+        *self = parsed_packet_ref.into_owned_with(buffer, options.max_packet_size);
+        self.scratch = scratch;
+        Ok(())
+    }
+
     /// Returns a reference to the packet
     #[inline]
     pub fn packet(&self) -> &[u8] {
@@ -73,6 +110,65 @@ impl ParsedPacket {
         self.packet.as_mut().unwrap()
     }
 
+    /// Writes the packet to a TCP or TLS stream, prefixed with its length.
+    /// See `TcpFramer` for the reverse operation.
+    pub fn write_tcp(&self, writer: &mut impl Write) -> Result<(), Error> {
+        let packet = self.packet();
+        if packet.len() > DNS_MAX_TCP_SIZE {
+            bail!(DSError::PacketTooLarge);
+        }
+        let mut framed = vec![0u8; DNS_TCP_LENGTH_PREFIX_SIZE];
+        framed.reserve(packet.len());
+        BigEndian::write_u16(&mut framed, packet.len() as u16);
+        framed.extend_from_slice(packet);
+        writer.write_all(&framed)?;
+        Ok(())
+    }
+
+    /// Returns a read-only view of the packet.
+    pub fn as_packet_ref(&self) -> ParsedPacketRef<'_> {
+        ParsedPacketRef {
+            packet: self.packet(),
+            offset_question: self.offset_question,
+            offset_answers: self.offset_answers,
+            offset_nameservers: self.offset_nameservers,
+            offset_additional: self.offset_additional,
+            offset_edns: self.offset_edns,
+            edns_count: self.edns_count,
+            ext_rcode: self.ext_rcode,
+            edns_version: self.edns_version,
+            ext_flags: self.ext_flags,
+            max_payload: self.max_payload,
+        }
+    }
+
+    /// Iterates over the question section, yielding read-only `RecordRef` views.
+    pub fn iter_question(&self) -> Records<'_> {
+        self.as_packet_ref().iter_question()
+    }
+
+    /// Iterates over the answer section, yielding read-only `RecordRef` views.
+    pub fn iter_answer(&self) -> Records<'_> {
+        self.as_packet_ref().iter_answer()
+    }
+
+    /// Iterates over the list of name servers, yielding read-only `RecordRef` views.
+    pub fn iter_nameservers(&self) -> Records<'_> {
+        self.as_packet_ref().iter_nameservers()
+    }
+
+    /// Iterates over the additional section, yielding read-only `RecordRef` views.
+    /// OPT RRs are skipped.
+    pub fn iter_additional(&self) -> Records<'_> {
+        self.as_packet_ref().iter_additional()
+    }
+
+    /// Iterates over the additional section, yielding read-only `RecordRef` views.
+    /// OPT RRs are included.
+    pub fn iter_additional_including_opt(&self) -> Records<'_> {
+        self.as_packet_ref().iter_additional_including_opt()
+    }
+
     /// Iterates over the question section.
     pub fn into_iter_question(&mut self) -> Option<QuestionIterator<'_>> {
         QuestionIterator::new(RRIterator::new(self, Section::Question)).next()
@@ -103,6 +199,34 @@ impl ParsedPacket {
         EdnsIterator::new(RRIterator::new(self, Section::Edns)).next()
     }
 
+    /// Returns a cursor over the question section, positioned before the first question.
+    /// Unlike `into_iter_question()`, the cursor is moved in place, using
+    /// `while it.advance() { ... }` loops.
+    pub fn cursor_question(&mut self) -> QuestionIterator<'_> {
+        QuestionIterator::new(RRIterator::new(self, Section::Question))
+    }
+
+    /// Returns a cursor over the answer section, positioned before the first record.
+    pub fn cursor_answer(&mut self) -> AnswerIterator<'_> {
+        AnswerIterator::new(RRIterator::new(self, Section::Answer))
+    }
+
+    /// Returns a cursor over the list of name servers, positioned before the first record.
+    pub fn cursor_nameservers(&mut self) -> NameServersIterator<'_> {
+        NameServersIterator::new(RRIterator::new(self, Section::NameServers))
+    }
+
+    /// Returns a cursor over the additional section, positioned before the first record.
+    /// OPT RRs are skipped by `advance()`, and included by `advance_including_opt()`.
+    pub fn cursor_additional(&mut self) -> AdditionalIterator<'_> {
+        AdditionalIterator::new(RRIterator::new(self, Section::Additional))
+    }
+
+    /// Returns a cursor over the edns pseudo-section, positioned before the first record.
+    pub fn cursor_edns(&mut self) -> EdnsIterator<'_> {
+        EdnsIterator::new(RRIterator::new(self, Section::Edns))
+    }
+
     /// Copy the packet header
     pub fn copy_header(&self, header: &mut Vec<u8>) {
         header.extend(&self.packet()[..DNS_HEADER_SIZE]);
@@ -189,6 +313,24 @@ impl ParsedPacket {
         BigEndian::write_u16(&mut self.packet_mut()[DNS_FLAGS_OFFSET..], oll);
     }
 
+    /// Check if the response was truncated
+    #[inline]
+    pub fn is_truncated(&self) -> bool {
+        self.flags() & DNS_FLAG_TC == DNS_FLAG_TC
+    }
+
+    /// Set the truncation bit
+    #[inline]
+    pub fn set_truncated(&mut self, is_truncated: bool) {
+        let mut oll = BigEndian::read_u16(&self.packet()[DNS_FLAGS_OFFSET..]);
+        if is_truncated {
+            oll |= DNS_FLAG_TC as u16
+        } else {
+            oll &= !(DNS_FLAG_TC as u16)
+        }
+        BigEndian::write_u16(&mut self.packet_mut()[DNS_FLAGS_OFFSET..], oll);
+    }
+
     /// Returns the return code.
     #[inline]
     pub fn rcode(&self) -> u8 {
@@ -197,12 +339,38 @@ impl ParsedPacket {
     }
 
     /// Changes the return code.
+    /// Only the lowest 4 bits are stored in the header - See `set_extended_rcode()`
+    /// for return codes that don't fit.
     pub fn set_rcode(&mut self, rcode: u8) {
         let p = &mut self.packet_mut()[DNS_FLAGS_OFFSET + 1];
         *p &= !0x0f;
         *p |= rcode & 0x0f;
     }
 
+    /// Returns the 12-bit extended return code, combining the header bits
+    /// with the bits stored in the OPT record.
+    pub fn extended_rcode(&self) -> u16 {
+        (self.ext_rcode.unwrap_or(0) as u16) << 4 | self.rcode() as u16
+    }
+
+    /// Changes the 12-bit extended return code.
+    /// An OPT record is added if the return code doesn't fit in the header.
+    pub fn set_extended_rcode(&mut self, rcode: u16) -> Result<(), Error> {
+        if rcode > 0xfff {
+            bail!(DSError::InvalidPacket("Extended return code too large"));
+        }
+        let ext_rcode = (rcode >> 4) as u8;
+        if ext_rcode != 0 && self.offset_edns.is_none() {
+            self.ensure_edns(DNS_DEFAULT_EDNS_PAYLOAD, 0, false)?;
+        }
+        if let Some(offset_opt_rr) = self.offset_opt_rr() {
+            self.packet_mut()[offset_opt_rr + 1 + DNS_OPT_RR_EXT_RCODE_OFFSET] = ext_rcode;
+            self.ext_rcode = Some(ext_rcode);
+        }
+        self.set_rcode((rcode & 0x0f) as u8);
+        Ok(())
+    }
+
     /// Returns the opcode.
     #[inline]
     pub fn opcode(&self) -> u8 {
@@ -223,19 +391,24 @@ impl ParsedPacket {
         self.max_payload
     }
 
+    /// Maximum size the packet can grow to when it is modified.
+    #[inline]
+    pub fn max_packet_size(&self) -> usize {
+        self.max_packet_size
+    }
+
+    /// Changes the maximum size the packet can grow to when it is modified.
+    /// The default is `DNS_MAX_UNCOMPRESSED_SIZE`, suitable for UDP. Packets sent over TCP
+    /// can use up to `DNS_MAX_TCP_SIZE`, which is the largest size that can be set.
+    pub fn set_max_packet_size(&mut self, max_packet_size: usize) {
+        self.max_packet_size = cmp::min(max_packet_size, DNS_MAX_TCP_SIZE);
+    }
+
     /// Increments the number of records in a given section
     pub fn rrcount_inc(&mut self, section: Section) -> Result<u16, Error> {
         let mut packet = &mut self.packet_mut();
         let mut rrcount = match section {
-            Section::Question => {
-                let rrcount = DNSSector::qdcount(&packet);
-                if rrcount >= 1 {
-                    bail!(DSError::InvalidPacket(
-                        "A DNS packet can only contain up to one question"
-                    ));
-                }
-                rrcount
-            }
+            Section::Question => DNSSector::qdcount(&packet),
             Section::Answer => DNSSector::ancount(&packet),
             Section::NameServers => DNSSector::nscount(&packet),
             Section::Additional => DNSSector::arcount(&packet),
@@ -299,24 +472,20 @@ impl ParsedPacket {
             Section::NameServers => self
                 .offset_additional
                 .unwrap_or_else(|| self.packet().len()),
-            Section::Additional => self.packet().len(),
+            Section::Additional => self.offset_tsig_rr().unwrap_or_else(|| self.packet().len()),
             _ => panic!("insertion_offset() is not suitable to adding EDNS pseudorecords"),
         };
         Ok(offset)
     }
 
     pub fn insert_rr(&mut self, section: Section, rr: gen::RR) -> Result<(), Error> {
-        if self.maybe_compressed {
-            let uncompressed = Compress::uncompress(&self.packet())?;
-            self.packet = Some(uncompressed);
-            self.recompute()?;
-            debug_assert_eq!(self.maybe_compressed, false);
-        }
+        self.uncompress()?;
         let rr_len = rr.packet.len();
-        if DNS_MAX_UNCOMPRESSED_SIZE - self.packet().len() < rr_len {
+        if self.packet().len() + rr_len > self.max_packet_size {
             bail!(DSError::PacketTooLarge)
         }
         let insertion_offset = self.insertion_offset(section)?;
+        self.rrcount_inc(section)?;
         let packet_len = self.packet().len();
         let new_len = packet_len + rr_len;
         self.packet_mut().reserve(rr_len);
@@ -328,7 +497,6 @@ impl ParsedPacket {
             packet.copy_within(insertion_offset..packet_len, insertion_offset + rr_len);
             packet[insertion_offset..insertion_offset + rr_len].copy_from_slice(&rr.packet);
         }
-        self.rrcount_inc(section)?;
         match section {
             Section::Question => {
                 self.offset_question = self.offset_question.or(Some(insertion_offset));
@@ -364,6 +532,558 @@ impl ParsedPacket {
         self.insert_rr(section, rr)
     }
 
+    /// Decompresses the packet, if it may contain compressed names.
+    pub fn uncompress(&mut self) -> Result<(), Error> {
+        if !self.maybe_compressed {
+            return Ok(());
+        }
+        let packet = self.packet.as_mut().unwrap();
+        Compress::uncompress_into(packet, &mut self.scratch)?;
+        mem::swap(packet, &mut self.scratch);
+        self.recompute()?;
+        debug_assert!(!self.maybe_compressed);
+        Ok(())
+    }
+
+    /// Returns the offset of the OPT record, if the packet contains one.
+    fn offset_opt_rr(&self) -> Option<usize> {
+        let offset_edns = self.offset_edns?;
+        debug_assert!(offset_edns >= 1 + DNS_RR_HEADER_SIZE);
+        let offset_opt_rr = offset_edns - (1 + DNS_RR_HEADER_SIZE);
+        debug_assert_eq!(self.packet()[offset_opt_rr], 0);
+        Some(offset_opt_rr)
+    }
+
+    /// Returns the offset of the TSIG record, if the packet contains one.
+    /// A TSIG record is always the last record of the additional section.
+    fn offset_tsig_rr(&self) -> Option<usize> {
+        match self.as_packet_ref().iter_additional().last() {
+            Some(record) if record.rr_type() == Type::TSIG.into() => Some(record.offset()),
+            _ => None,
+        }
+    }
+
+    /// Returns the length of the data stored in the OPT record.
+    fn opt_rr_rdlen(&self, offset_opt_rr: usize) -> usize {
+        BigEndian::read_u16(&self.packet()[offset_opt_rr + 1 + DNS_OPT_RR_RDLEN_OFFSET..]) as usize
+    }
+
+    /// Names stored after the OPT record may be referenced by records following it.
+    /// Decompresses the packet if the OPT record is not the last record, so that
+    /// it can be safely resized. A TSIG record can follow it, since it can only refer
+    /// to names stored before the OPT record.
+    fn prepare_opt_rr_resize(&mut self) -> Result<(), Error> {
+        if !self.maybe_compressed {
+            return Ok(());
+        }
+        if let Some(offset_opt_rr) = self.offset_opt_rr() {
+            let opt_rr_end =
+                offset_opt_rr + 1 + DNS_OPT_RR_HEADER_SIZE + self.opt_rr_rdlen(offset_opt_rr);
+            if opt_rr_end == self.packet().len() || Some(opt_rr_end) == self.offset_tsig_rr() {
+                return Ok(());
+            }
+        }
+        self.uncompress()
+    }
+
+    /// Moves the OPT record to the end of the additional section, before the TSIG record
+    /// if there is one, so that it can be resized without decompressing the packet.
+    fn move_opt_rr_last(&mut self) -> Result<(), Error> {
+        let offset_opt_rr = match self.offset_opt_rr() {
+            None => return Ok(()),
+            Some(offset_opt_rr) => offset_opt_rr,
+        };
+        let opt_rr_len = 1 + DNS_OPT_RR_HEADER_SIZE + self.opt_rr_rdlen(offset_opt_rr);
+        let end = self.offset_tsig_rr().unwrap_or_else(|| self.packet().len());
+        if offset_opt_rr + opt_rr_len == end {
+            return Ok(());
+        }
+        self.uncompress()?;
+        let offset_opt_rr = self.offset_opt_rr().expect("OPT record vanished");
+        let end = self.offset_tsig_rr().unwrap_or_else(|| self.packet().len());
+        let packet = self.packet_mut();
+        packet[offset_opt_rr..end].rotate_left(opt_rr_len);
+        self.offset_edns = Some(end - opt_rr_len + 1 + DNS_OPT_RR_HEADER_SIZE);
+        Ok(())
+    }
+
+    /// Adds an OPT record to the additional section, or updates the existing one.
+    /// `max_payload` is the maximum UDP payload size to advertise, and `do_bit`
+    /// sets or clears the `DO` (DNSSEC OK) flag.
+    /// Other extended flags, the extended return code and edns options are preserved.
+    /// A new OPT record is inserted before the TSIG record, if the packet is signed.
+    pub fn ensure_edns(
+        &mut self,
+        max_payload: u16,
+        version: u8,
+        do_bit: bool,
+    ) -> Result<(), Error> {
+        let mut ext_flags = self.ext_flags.unwrap_or(0);
+        let do_flag = (DNS_FLAG_DO >> 16) as u16;
+        if do_bit {
+            ext_flags |= do_flag;
+        } else {
+            ext_flags &= !do_flag;
+        }
+        match self.offset_opt_rr() {
+            Some(offset_opt_rr) => {
+                let opt_rr = &mut self.packet_mut()[offset_opt_rr + 1..];
+                BigEndian::write_u16(&mut opt_rr[DNS_OPT_RR_MAX_PAYLOAD_OFFSET..], max_payload);
+                opt_rr[DNS_OPT_RR_EDNS_VERSION_OFFSET] = version;
+                BigEndian::write_u16(&mut opt_rr[DNS_OPT_RR_EDNS_EXT_FLAGS_OFFSET..], ext_flags);
+            }
+            None => {
+                let mut opt_rr = [0u8; 1 + DNS_OPT_RR_HEADER_SIZE];
+                BigEndian::write_u16(&mut opt_rr[1 + DNS_RR_TYPE_OFFSET..], Type::OPT.into());
+                BigEndian::write_u16(
+                    &mut opt_rr[1 + DNS_OPT_RR_MAX_PAYLOAD_OFFSET..],
+                    max_payload,
+                );
+                opt_rr[1 + DNS_OPT_RR_EDNS_VERSION_OFFSET] = version;
+                BigEndian::write_u16(
+                    &mut opt_rr[1 + DNS_OPT_RR_EDNS_EXT_FLAGS_OFFSET..],
+                    ext_flags,
+                );
+                if self.packet().len() + opt_rr.len() > self.max_packet_size {
+                    bail!(DSError::PacketTooLarge)
+                }
+                let insertion_offset = self.insertion_offset(Section::Additional)?;
+                self.rrcount_inc(Section::Additional)?;
+                self.packet_mut()
+                    .splice(insertion_offset..insertion_offset, opt_rr.iter().cloned());
+                self.offset_additional = self.offset_additional.or(Some(insertion_offset));
+                self.offset_edns = Some(insertion_offset + opt_rr.len());
+                self.edns_count = 0;
+                self.ext_rcode = Some(0);
+            }
+        }
+        self.max_payload = max_payload as usize;
+        self.edns_version = Some(version);
+        self.ext_flags = Some(ext_flags);
+        Ok(())
+    }
+
+    /// Removes the OPT record, along with all the edns options it contains.
+    /// Returns `false` if the packet didn't contain any OPT record.
+    pub fn remove_edns(&mut self) -> Result<bool, Error> {
+        if self.offset_edns.is_none() {
+            return Ok(false);
+        }
+        self.prepare_opt_rr_resize()?;
+        let offset_opt_rr = self.offset_opt_rr().expect("OPT record vanished");
+        let opt_rr_len = 1 + DNS_OPT_RR_HEADER_SIZE + self.opt_rr_rdlen(offset_opt_rr);
+        self.packet_mut()
+            .drain(offset_opt_rr..offset_opt_rr + opt_rr_len);
+        if self.rrcount_dec(Section::Additional)? == 0 {
+            self.offset_additional = None;
+        }
+        self.offset_edns = None;
+        self.edns_count = 0;
+        self.ext_rcode = None;
+        self.edns_version = None;
+        self.ext_flags = None;
+        self.max_payload = DNS_DEFAULT_MAX_PAYLOAD;
+        Ok(true)
+    }
+
+    /// Returns the location of the first edns option with the given code,
+    /// including its header, as well as the offset right after the last edns option.
+    fn edns_option_range(&self, code: u16) -> Option<(Option<(usize, usize)>, usize)> {
+        let offset_opt_rr = self.offset_opt_rr()?;
+        let packet = self.packet();
+        let mut offset = self.offset_edns?;
+        let edns_end = offset + self.opt_rr_rdlen(offset_opt_rr);
+        let mut found = None;
+        while offset < edns_end {
+            let offset_next = RRIterator::edns_skip_rr(packet, offset);
+            if found.is_none()
+                && BigEndian::read_u16(&packet[offset + DNS_EDNS_RR_CODE_OFFSET..]) == code
+            {
+                found = Some((offset, offset_next));
+            }
+            offset = offset_next;
+        }
+        debug_assert_eq!(offset, edns_end);
+        Some((found, edns_end))
+    }
+
+    /// Replaces the bytes between `start` and `end` in the edns section with `data`,
+    /// and updates the length of the OPT record accordingly.
+    fn splice_edns_section(&mut self, start: usize, end: usize, data: &[u8]) -> Result<(), Error> {
+        let offset_opt_rr = self.offset_opt_rr().expect("OPT record vanished");
+        let new_rdlen = self.opt_rr_rdlen(offset_opt_rr) - (end - start) + data.len();
+        if new_rdlen > 0xffff {
+            bail!(DSError::InvalidPacket("Edns section too large"));
+        }
+        if self.packet().len() - (end - start) + data.len() > self.max_packet_size {
+            bail!(DSError::PacketTooLarge)
+        }
+        self.packet_mut().splice(start..end, data.iter().cloned());
+        BigEndian::write_u16(
+            &mut self.packet_mut()[offset_opt_rr + 1 + DNS_OPT_RR_RDLEN_OFFSET..],
+            new_rdlen as u16,
+        );
+        Ok(())
+    }
+
+    /// Returns the data of the first edns option with the given code.
+    pub fn edns_option(&self, code: u16) -> Option<&[u8]> {
+        self.as_packet_ref().edns_option(code)
+    }
+
+    /// Returns the data of all the edns options with the given code, in order.
+    pub fn edns_options(&self, code: u16) -> Vec<&[u8]> {
+        self.as_packet_ref().edns_options(code)
+    }
+
+    /// Sets the data of an edns option. If an option with the same code is already present,
+    /// its data is replaced. Otherwise, a new option is appended to the edns section.
+    /// The packet must already contain an OPT record - see `ensure_edns()`.
+    pub fn set_edns_option(&mut self, code: u16, data: &[u8]) -> Result<(), Error> {
+        self.insert_edns_option(code, data, true)
+    }
+
+    /// Appends an edns option, even if options with the same code are already present.
+    /// The packet must already contain an OPT record - see `ensure_edns()`.
+    pub fn add_edns_option(&mut self, code: u16, data: &[u8]) -> Result<(), Error> {
+        self.insert_edns_option(code, data, false)
+    }
+
+    fn insert_edns_option(&mut self, code: u16, data: &[u8], replace: bool) -> Result<(), Error> {
+        if data.len() > 0xffff {
+            bail!(DSError::InvalidPacket("Edns option too large"));
+        }
+        if self.offset_edns.is_none() {
+            bail!(DSError::PropertyNotFound);
+        }
+        self.prepare_opt_rr_resize()?;
+        let (found, edns_end) = self.edns_option_range(code).expect("OPT record vanished");
+        let mut option = Vec::with_capacity(DNS_EDNS_RR_HEADER_SIZE + data.len());
+        option.extend_from_slice(&[0u8; DNS_EDNS_RR_HEADER_SIZE]);
+        BigEndian::write_u16(&mut option[DNS_EDNS_RR_CODE_OFFSET..], code);
+        BigEndian::write_u16(&mut option[DNS_EDNS_RR_RDLEN_OFFSET..], data.len() as u16);
+        option.extend_from_slice(data);
+        match found {
+            Some((start, end)) if replace => self.splice_edns_section(start, end, &option)?,
+            _ => {
+                self.splice_edns_section(edns_end, edns_end, &option)?;
+                self.edns_count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes all the edns options with the given code.
+    /// Returns `false` if no such options were present.
+    pub fn remove_edns_option(&mut self, code: u16) -> Result<bool, Error> {
+        match self.edns_option_range(code) {
+            Some((Some(_), _)) => {}
+            _ => return Ok(false),
+        }
+        self.prepare_opt_rr_resize()?;
+        while let Some((Some((start, end)), _)) = self.edns_option_range(code) {
+            self.splice_edns_section(start, end, &[])?;
+            self.edns_count -= 1;
+        }
+        Ok(true)
+    }
+
+    /// Returns all the extended DNS errors, in order.
+    pub fn extended_errors(&self) -> Result<Vec<ExtendedErrorInfo>, Error> {
+        self.as_packet_ref().extended_errors()
+    }
+
+    /// Adds an extended DNS error, along with an optional text (that can be empty).
+    /// The packet must already contain an OPT record - see `ensure_edns()`.
+    pub fn add_extended_error(
+        &mut self,
+        error: ExtendedError,
+        extra_text: &str,
+    ) -> Result<(), Error> {
+        let data = ExtendedErrorInfo::new(error, extra_text).encode();
+        self.add_edns_option(EdnsOption::EDE.into(), &data)
+    }
+
+    /// Returns the edns client subnet option, if the packet contains one.
+    pub fn client_subnet(&self) -> Result<Option<ClientSubnet>, Error> {
+        self.as_packet_ref().client_subnet()
+    }
+
+    /// Adds or replaces the edns client subnet option.
+    /// The packet must already contain an OPT record - see `ensure_edns()`.
+    pub fn set_client_subnet(&mut self, client_subnet: &ClientSubnet) -> Result<(), Error> {
+        let data = client_subnet.encode()?;
+        self.set_edns_option(EdnsOption::CLIENT_SUBNET.into(), &data)
+    }
+
+    /// Adds or replaces the edns client subnet option with the subnet of `ip`,
+    /// truncated to `v4_prefix` or `v6_prefix` bits depending on the address family.
+    pub fn set_client_subnet_from_ip(
+        &mut self,
+        ip: IpAddr,
+        v4_prefix: u8,
+        v6_prefix: u8,
+    ) -> Result<(), Error> {
+        let source_prefix = match ip {
+            IpAddr::V4(_) => v4_prefix,
+            IpAddr::V6(_) => v6_prefix,
+        };
+        self.set_client_subnet(&ClientSubnet::from_ip(ip, source_prefix))
+    }
+
+    /// Returns the cookie option, if the packet contains one.
+    pub fn cookie(&self) -> Result<Option<Cookie>, Error> {
+        self.as_packet_ref().cookie()
+    }
+
+    /// Returns the TSIG record, if the packet contains one.
+    pub fn tsig(&self) -> Result<Option<TsigRecord>, Error> {
+        self.as_packet_ref().tsig()
+    }
+
+    /// Signs the packet, by appending a TSIG record to the additional section.
+    /// The packet is uncompressed beforehand. Records added after that are inserted before
+    /// the TSIG record, but invalidate the signature.
+    /// `time` is expressed in seconds since the UNIX epoch. Returns the MAC.
+    pub fn tsig_sign(
+        &mut self,
+        key_name: &[u8],
+        algorithm: TsigAlgorithm,
+        secret: &[u8],
+        time: u64,
+        fudge: u16,
+    ) -> Result<Vec<u8>, Error> {
+        self.tsig_sign_chained(key_name, algorithm, secret, time, fudge, None)
+    }
+
+    /// Signs a response to a signed request, or a message from a multi-message response,
+    /// given the MAC of the previous message - See `tsig_sign()`.
+    pub fn tsig_sign_chained(
+        &mut self,
+        key_name: &[u8],
+        algorithm: TsigAlgorithm,
+        secret: &[u8],
+        time: u64,
+        fudge: u16,
+        chain: Option<TsigChain<'_>>,
+    ) -> Result<Vec<u8>, Error> {
+        if self.tsig()?.is_some() {
+            bail!(DSError::InvalidPacket("The packet is already signed"));
+        }
+        self.uncompress()?;
+        let mut tsig = TsigRecord {
+            key_name: key_name.to_ascii_lowercase(),
+            algorithm: algorithm.name().to_vec(),
+            time_signed: time & 0xffff_ffff_ffff,
+            fudge,
+            mac: vec![],
+            original_id: self.tid(),
+            error: 0,
+            other_data: vec![],
+        };
+        tsig.mac = tsig.compute_mac(algorithm, secret, self.packet(), chain)?;
+        let rr_header = gen::RRHeader {
+            name: tsig.key_name.clone(),
+            ttl: 0,
+            class: Class::ANY,
+            rr_type: Type::TSIG,
+        };
+        let rr = gen::RR::new(rr_header, &tsig.encode_rdata()?)?;
+        self.insert_rr(Section::Additional, rr)?;
+        Ok(tsig.mac)
+    }
+
+    /// Verifies the TSIG record of the packet using a key from `keyring`, given the current
+    /// time in seconds since the UNIX epoch.
+    /// The TSIG record is removed from the packet, and the original transaction ID is restored.
+    pub fn tsig_verify(&mut self, keyring: &[TsigKey], now: u64) -> Result<TsigStatus, Error> {
+        self.tsig_verify_chained(keyring, now, None)
+    }
+
+    /// Verifies a response to a signed request, or a message from a multi-message response,
+    /// given the MAC of the previous message - See `tsig_verify()`.
+    pub fn tsig_verify_chained(
+        &mut self,
+        keyring: &[TsigKey],
+        now: u64,
+        chain: Option<TsigChain<'_>>,
+    ) -> Result<TsigStatus, Error> {
+        let (tsig, offset) = match self.as_packet_ref().iter_additional().last() {
+            Some(record) if record.rr_type() == Type::TSIG.into() => (
+                TsigRecord::decode(record.name(), record.rdata())?,
+                record.offset(),
+            ),
+            _ => return Ok(TsigStatus::Unsigned),
+        };
+        self.packet_mut().truncate(offset);
+        if self.rrcount_dec(Section::Additional)? == 0 {
+            self.offset_additional = None;
+        }
+        self.set_tid(tsig.original_id);
+        tsig.verify(keyring, self.packet(), now, chain)
+    }
+
+    /// Adds or replaces the cookie option.
+    /// The packet must already contain an OPT record - see `ensure_edns()`.
+    pub fn set_cookie(&mut self, cookie: &Cookie) -> Result<(), Error> {
+        self.set_edns_option(EdnsOption::COOKIE.into(), &cookie.encode())
+    }
+
+    /// Sets the `BADCOOKIE` extended return code.
+    pub fn set_badcookie(&mut self) -> Result<(), Error> {
+        self.set_extended_rcode(Rcode::BADCOOKIE.into())
+    }
+
+    /// Builds a response to this query, with the given extended return code.
+    /// The transaction ID, the opcode, the questions and the `RD` and `CD` flags are copied
+    /// from the query. If the query contained an OPT record, the response also includes
+    /// one, advertising a payload size of `DNS_DEFAULT_EDNS_PAYLOAD` bytes and mirroring
+    /// the `DO` flag.
+    pub fn to_response(&self, rcode: u16) -> Result<ParsedPacket, Error> {
+        self.to_response_with_payload(rcode, DNS_DEFAULT_EDNS_PAYLOAD)
+    }
+
+    /// Builds a response to this query, like `to_response()`, but advertising a payload
+    /// size of `max_payload` bytes if the response includes an OPT record.
+    pub fn to_response_with_payload(
+        &self,
+        rcode: u16,
+        max_payload: u16,
+    ) -> Result<ParsedPacket, Error> {
+        let mut response = ParsedPacket::empty();
+        response.set_tid(self.tid());
+        // `set_flags()` can only add flags, and `ParsedPacket::empty()` sets RD.
+        let flags = DNS_FLAG_QR | (self.flags() & (DNS_FLAG_RD | DNS_FLAG_CD));
+        BigEndian::write_u16(&mut response.packet_mut()[DNS_FLAGS_OFFSET..], flags as u16);
+        response.set_opcode(self.opcode());
+        if let Some(mut offset) = self.offset_question {
+            let packet = self.packet();
+            for _ in 0..DNSSector::qdcount(packet) {
+                let mut raw_name = Vec::with_capacity(DNS_MAX_HOSTNAME_LEN);
+                offset =
+                    Compress::copy_uncompressed_name(&mut raw_name, packet, offset).final_offset;
+                let rr_type = BigEndian::read_u16(&packet[offset + DNS_RR_TYPE_OFFSET..]);
+                let rr_class = BigEndian::read_u16(&packet[offset + DNS_RR_CLASS_OFFSET..]);
+                let rr = gen::RR::new_question_from_raw_name(&raw_name, rr_type, rr_class);
+                response.insert_rr(Section::Question, rr)?;
+                offset += DNS_RR_QUESTION_HEADER_SIZE;
+            }
+        }
+        if self.offset_edns.is_some() {
+            let do_bit = self.flags() & DNS_FLAG_DO != 0;
+            response.ensure_edns(max_payload, 0, do_bit)?;
+        }
+        response.set_extended_rcode(rcode)?;
+        Ok(response)
+    }
+
+    /// Builds a `REFUSED` response to this query.
+    pub fn refused(&self) -> Result<ParsedPacket, Error> {
+        self.to_response(Rcode::REFUSED.into())
+    }
+
+    /// Builds a `SERVFAIL` response to this query.
+    pub fn servfail(&self) -> Result<ParsedPacket, Error> {
+        self.to_response(Rcode::SERVFAIL.into())
+    }
+
+    /// Builds a `NXDOMAIN` response to this query, with `soa_rr` in the authority section.
+    pub fn nxdomain_with_soa(&self, soa_rr: gen::RR) -> Result<ParsedPacket, Error> {
+        let mut response = self.to_response(Rcode::NXDOMAIN.into())?;
+        response.insert_rr(Section::NameServers, soa_rr)?;
+        Ok(response)
+    }
+
+    /// Builds an empty `NOERROR` response to this query, with `soa_rr` in the authority section.
+    pub fn nodata_with_soa(&self, soa_rr: gen::RR) -> Result<ParsedPacket, Error> {
+        let mut response = self.to_response(Rcode::NOERROR.into())?;
+        response.insert_rr(Section::NameServers, soa_rr)?;
+        Ok(response)
+    }
+
+    /// Shrinks the packet so that it doesn't exceed `max_len` bytes.
+    /// The packet is compressed first, then whole RRsets are removed from the end of the
+    /// additional section, then from the authority section, and finally from the answer
+    /// section, until the packet fits. The OPT record is always kept.
+    /// The `TC` flag is set only if records from the answer section had to be removed.
+    /// If the packet still doesn't fit, no records are removed and `PacketTooLarge` is returned.
+    /// Signed packets are rejected, since their signature would no longer be valid: they
+    /// have to be truncated before being signed.
+    pub fn truncate_to(&mut self, max_len: usize) -> Result<(), Error> {
+        if self.offset_tsig_rr().is_some() {
+            bail!(DSError::InvalidPacket("Signed packets cannot be truncated"));
+        }
+        self.compress()?;
+        if self.packet().len() <= max_len {
+            return Ok(());
+        }
+        let original = self.packet().to_vec();
+        let mut answers_truncated = false;
+        for &section in &[Section::Additional, Section::NameServers, Section::Answer] {
+            while self.packet().len() > max_len {
+                if !self.remove_last_rrset(section)? {
+                    break;
+                }
+                if section == Section::Answer {
+                    answers_truncated = true;
+                }
+                self.compress()?;
+            }
+        }
+        if self.packet().len() > max_len {
+            self.reparse(original)?;
+            self.maybe_compressed = true;
+            bail!(DSError::PacketTooLarge)
+        }
+        if answers_truncated {
+            self.set_truncated(true);
+        }
+        Ok(())
+    }
+
+    /// Removes all the records of a section sharing the name, type and class of
+    /// the last record of that section. OPT records are ignored.
+    /// Returns `false` if the section didn't contain any records.
+    fn remove_last_rrset(&mut self, section: Section) -> Result<bool, Error> {
+        self.uncompress()?;
+        let mut last = None;
+        {
+            let mut it = self.cursor_section(section);
+            while it.advance() {
+                let mut name = Vec::with_capacity(DNS_MAX_HOSTNAME_LEN);
+                it.copy_raw_name(&mut name);
+                name.make_ascii_lowercase();
+                last = Some((name, it.rr_type(), it.rr_class()));
+            }
+        }
+        let (last_name, last_type, last_class) = match last {
+            None => return Ok(false),
+            Some(last) => last,
+        };
+        let mut it = self.cursor_section(section);
+        while it.advance() {
+            if it.rr_type() == last_type && it.rr_class() == last_class {
+                let mut name = Vec::with_capacity(DNS_MAX_HOSTNAME_LEN);
+                it.copy_raw_name(&mut name);
+                name.make_ascii_lowercase();
+                if name == last_name {
+                    it.delete()?;
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns a cursor over the answer, nameservers or additional section - OPT RRs are skipped.
+    fn cursor_section(&mut self, section: Section) -> ResponseIterator<'_> {
+        match section {
+            Section::Answer => self.cursor_answer(),
+            Section::NameServers => self.cursor_nameservers(),
+            Section::Additional => self.cursor_additional(),
+            _ => panic!("cursor_section() is only suitable to response sections"),
+        }
+    }
+
     /// Recomputes all section offsets after an in-place decompression of the packet.
     /// It is currently re-parsing everything by calling `parse()`, but this can be
     /// optimized later to skip over RDATA, and by assuming that the input
@@ -372,8 +1092,16 @@ impl ParsedPacket {
         if !self.maybe_compressed {
             return Ok(());
         }
-        let dns_sector = DNSSector::new(self.packet.take().expect("self.packet is None"))?;
-        let parsed_packet = dns_sector.parse()?;
+        let packet = self.packet.take().expect("self.packet is None");
+        self.reparse(packet)?;
+        self.maybe_compressed = false;
+        Ok(())
+    }
+
+    /// Replaces the packet with an equivalent packet, and recomputes all section offsets.
+    fn reparse(&mut self, packet: Vec<u8>) -> Result<(), Error> {
+        let dns_sector = DNSSector::new(packet)?;
+        let parsed_packet = dns_sector.parse_with(&ParseOptions::internal())?;
         self.offset_question = parsed_packet.offset_question;
         self.offset_answers = parsed_packet.offset_answers;
         self.offset_nameservers = parsed_packet.offset_nameservers;
@@ -383,13 +1111,41 @@ impl ParsedPacket {
         assert_eq!(self.ext_rcode, parsed_packet.ext_rcode);
         assert_eq!(self.edns_version, parsed_packet.edns_version);
         assert_eq!(self.ext_flags, parsed_packet.ext_flags);
-        self.maybe_compressed = false;
         self.packet = Some(parsed_packet.into_packet());
         self.cached = None;
         Ok(())
     }
 
-    /// Returns the question as a raw vector, without case conversion, as well as the query type and class
+    /// Compresses the packet, so that names are shared between records.
+    pub fn compress(&mut self) -> Result<(), Error> {
+        self.uncompress()?;
+        let packet = self.packet.as_mut().unwrap();
+        Compress::compress_into(packet, &mut self.scratch)?;
+        mem::swap(packet, &mut self.scratch);
+        let compressed = self.packet.take().expect("self.packet is None");
+        self.reparse(compressed)?;
+        self.maybe_compressed = true;
+        Ok(())
+    }
+
+    /// Adds an edns padding option, or resizes the existing one, so that the final size
+    /// of the packet matches the padding policy. The packet is compressed beforehand, and
+    /// the OPT record is moved to the end of the additional section.
+    /// The packet must already contain an OPT record - see `ensure_edns()`.
+    pub fn pad(&mut self, policy: PaddingPolicy) -> Result<(), Error> {
+        if self.offset_edns.is_none() {
+            bail!(DSError::PropertyNotFound);
+        }
+        self.remove_edns_option(EdnsOption::PADDING.into())?;
+        self.move_opt_rr_last()?;
+        self.compress()?;
+        self.prepare_opt_rr_resize()?;
+        let len = self.packet().len() + DNS_EDNS_RR_HEADER_SIZE;
+        let padding_len = policy.padding_len(len, self.is_response());
+        self.set_edns_option(EdnsOption::PADDING.into(), &vec![0u8; padding_len])
+    }
+
+    /// Returns the first question as a raw vector, without case conversion, as well as the query type and class
     /// Names include a trailing `0`
     pub fn question_raw0(&mut self) -> Option<(&[u8], u16, u16)> {
         if let Some(ref cached) = self.cached {
@@ -414,14 +1170,15 @@ impl ParsedPacket {
         Some((&cached.0, cached.1, cached.2))
     }
 
-    /// Returns the question as a raw vector, without case conversion, as well as the query type and class
+    /// Returns the first question as a raw vector, without case conversion, as well as the query type and class
     /// Names do not include trailing `0`
     pub fn question_raw(&mut self) -> Option<(&[u8], u16, u16)> {
         self.question_raw0()
             .map(|(name, rr_type, rr_class)| (&name[..name.len() - 1], rr_type, rr_class))
     }
 
-    /// Returns the question as a string, without case conversion, as well as the query type and class
+    /// Returns the first question as a lower-case string, as well as the query type and class.
+    /// Use `question_raw()` to get the name as it appears in the packet.
     pub fn question(&mut self) -> Option<(Vec<u8>, u16, u16)> {
         if let Some(ref cached) = self.cached {
             let mut name_str = Compress::raw_name_to_str(&cached.0, 0);
@@ -444,7 +1201,13 @@ impl ParsedPacket {
         Some((name_str, rr_type, rr_class))
     }
 
-    /// Return the query type and class
+    /// Returns all the questions as lower-case strings, as well as their query types and classes.
+    /// Most packets contain a single question, which is better accessed with `question()`.
+    pub fn questions(&self) -> Vec<(Vec<u8>, u16, u16)> {
+        self.as_packet_ref().questions()
+    }
+
+    /// Return the query type and class of the first question
     pub fn qtype_qclass(&self) -> Option<(u16, u16)> {
         if let Some(ref cached) = self.cached {
             return Some((cached.1, cached.2));
@@ -473,18 +1236,7 @@ impl ParsedPacket {
         match_suffix: bool,
     ) -> Result<(), Error> {
         let packet = Renamer::rename_with_raw_names(self, target_name, source_name, match_suffix)?;
-        self.packet = Some(packet);
-        let dns_sector = DNSSector::new(self.packet.take().unwrap())?;
-        let parsed_packet = dns_sector.parse()?; // XXX - This can be recomputed on the fly by Renamer::rename_with_raw_names()
-        self.offset_question = parsed_packet.offset_question;
-        self.offset_answers = parsed_packet.offset_answers;
-        self.offset_nameservers = parsed_packet.offset_nameservers;
-        self.offset_additional = parsed_packet.offset_additional;
-        self.offset_edns = parsed_packet.offset_edns;
-        assert_eq!(self.edns_count, parsed_packet.edns_count);
-        assert_eq!(self.ext_rcode, parsed_packet.ext_rcode);
-        assert_eq!(self.edns_version, parsed_packet.edns_version);
-        assert_eq!(self.ext_flags, parsed_packet.ext_flags);
+        self.reparse(packet)?; // XXX - This can be recomputed on the fly by Renamer::rename_with_raw_names()
         self.maybe_compressed = true;
         Ok(())
     }
@@ -0,0 +1,377 @@
+use crate::client_subnet::*;
+use crate::compress::*;
+use crate::constants::*;
+use crate::cookie::*;
+use crate::dns_sector::*;
+use crate::errors::*;
+use crate::extended_error::*;
+use crate::parsed_packet::*;
+use crate::rr_iterator::*;
+use crate::tsig::*;
+use byteorder::{BigEndian, ByteOrder};
+
+/// A `ParsedPacketRef` structure gives read-only access to a successfully parsed DNS packet,
+/// without owning it. It is returned by `DNSSector::parse_ref()`, and can be upgraded to a
+/// `ParsedPacket` using `into_owned()` in order to modify the packet.
+#[derive(Clone, Copy, Debug)]
+pub struct ParsedPacketRef<'a> {
+    pub packet: &'a [u8],
+    pub offset_question: Option<usize>,
+    pub offset_answers: Option<usize>,
+    pub offset_nameservers: Option<usize>,
+    pub offset_additional: Option<usize>,
+    pub offset_edns: Option<usize>,
+    pub edns_count: u16,
+    pub ext_rcode: Option<u8>,
+    pub edns_version: Option<u8>,
+    pub ext_flags: Option<u16>,
+    pub max_payload: usize,
+}
+
+impl<'a> ParsedPacketRef<'a> {
+    /// Copies the packet into a `ParsedPacket`, that can be modified.
+    /// It can grow up to `DNS_MAX_UNCOMPRESSED_SIZE` bytes - See `ParsedPacket::set_max_packet_size()`.
+    pub fn into_owned(self) -> ParsedPacket {
+        self.into_owned_with(
+            Vec::with_capacity(self.packet.len()),
+            DNS_MAX_UNCOMPRESSED_SIZE,
+        )
+    }
+
+    /// Copies the packet into a `ParsedPacket`, using `buffer` as a storage.
+    pub(crate) fn into_owned_with(
+        self,
+        mut buffer: Vec<u8>,
+        max_packet_size: usize,
+    ) -> ParsedPacket {
+        buffer.clear();
+        buffer.extend_from_slice(self.packet);
+        ParsedPacket {
+            packet: Some(buffer),
+            offset_question: self.offset_question,
+            offset_answers: self.offset_answers,
+            offset_nameservers: self.offset_nameservers,
+            offset_additional: self.offset_additional,
+            offset_edns: self.offset_edns,
+            edns_count: self.edns_count,
+            ext_rcode: self.ext_rcode,
+            edns_version: self.edns_version,
+            ext_flags: self.ext_flags,
+            maybe_compressed: true,
+            max_payload: self.max_payload,
+            max_packet_size,
+            cached: None,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the packet
+    #[inline]
+    pub fn packet(&self) -> &'a [u8] {
+        self.packet
+    }
+
+    /// Returns the transaction ID.
+    #[inline]
+    pub fn tid(&self) -> u16 {
+        BigEndian::read_u16(&self.packet[DNS_TID_OFFSET..])
+    }
+
+    /// Returns the flags, including extended flags - See `ParsedPacket::flags()`.
+    pub fn flags(&self) -> u32 {
+        let mut rflags = BigEndian::read_u16(&self.packet[DNS_FLAGS_OFFSET..]);
+        rflags &= !0x7800; // mask opcode
+        rflags &= !0x000f; // mask rcode
+        (self.ext_flags.unwrap_or(0) as u32) << 16 | (rflags as u32)
+    }
+
+    /// Check if this is a question with the DO bit, or a response with the AD bit
+    pub fn dnssec(&self) -> bool {
+        let flags = self.flags();
+        if flags & DNS_FLAG_QR == 0 {
+            (flags & DNS_FLAG_DO) != 0
+        } else {
+            (flags & DNS_FLAG_AD) != 0
+        }
+    }
+
+    /// Check if this is a response
+    #[inline]
+    pub fn is_response(&self) -> bool {
+        self.flags() & DNS_FLAG_QR == DNS_FLAG_QR
+    }
+
+    /// Returns the return code.
+    #[inline]
+    pub fn rcode(&self) -> u8 {
+        self.packet[DNS_FLAGS_OFFSET + 1] & 0x0f
+    }
+
+    /// Returns the 12-bit extended return code.
+    pub fn extended_rcode(&self) -> u16 {
+        (self.ext_rcode.unwrap_or(0) as u16) << 4 | self.rcode() as u16
+    }
+
+    /// Returns the opcode.
+    #[inline]
+    pub fn opcode(&self) -> u8 {
+        (self.packet[DNS_FLAGS_OFFSET] & 0x78) >> 3
+    }
+
+    /// Maximum payload size when using UDP
+    #[inline]
+    pub fn max_payload(&self) -> usize {
+        self.max_payload
+    }
+
+    /// Returns the first question as a lower-case string, as well as the query type and class.
+    pub fn question(&self) -> Option<(Vec<u8>, u16, u16)> {
+        self.iter_question()
+            .next()
+            .map(|question| (question.name(), question.rr_type(), question.rr_class()))
+    }
+
+    /// Returns all the questions as lower-case strings, as well as their query types and classes.
+    pub fn questions(&self) -> Vec<(Vec<u8>, u16, u16)> {
+        self.iter_question()
+            .map(|question| (question.name(), question.rr_type(), question.rr_class()))
+            .collect()
+    }
+
+    /// Return the query type and class of the first question
+    pub fn qtype_qclass(&self) -> Option<(u16, u16)> {
+        self.iter_question()
+            .next()
+            .map(|question| (question.rr_type(), question.rr_class()))
+    }
+
+    /// Iterates over the question section.
+    pub fn iter_question(&self) -> Records<'a> {
+        Records::new(self.packet, Section::Question, self.offset_question)
+    }
+
+    /// Iterates over the answer section.
+    pub fn iter_answer(&self) -> Records<'a> {
+        Records::new(self.packet, Section::Answer, self.offset_answers)
+    }
+
+    /// Iterates over the list of name servers.
+    pub fn iter_nameservers(&self) -> Records<'a> {
+        Records::new(self.packet, Section::NameServers, self.offset_nameservers)
+    }
+
+    /// Iterates over the additional section - OPT RRs are skipped.
+    pub fn iter_additional(&self) -> Records<'a> {
+        Records::new(self.packet, Section::Additional, self.offset_additional)
+    }
+
+    /// Iterates over the additional section - OPT RRs are included.
+    pub fn iter_additional_including_opt(&self) -> Records<'a> {
+        let mut records = self.iter_additional();
+        records.include_opt = true;
+        records
+    }
+
+    /// Returns the data of the first edns option with the given code.
+    pub fn edns_option(&self, code: u16) -> Option<&'a [u8]> {
+        self.edns_options(code).into_iter().next()
+    }
+
+    /// Returns the data of all the edns options with the given code, in order.
+    pub fn edns_options(&self, code: u16) -> Vec<&'a [u8]> {
+        let mut options = vec![];
+        let mut offset = match self.offset_edns {
+            None => return options,
+            Some(offset_edns) => offset_edns,
+        };
+        let packet = self.packet;
+        let rdlen_offset = offset - (1 + DNS_RR_HEADER_SIZE) + 1 + DNS_OPT_RR_RDLEN_OFFSET;
+        let edns_end = offset + BigEndian::read_u16(&packet[rdlen_offset..]) as usize;
+        while offset < edns_end {
+            let offset_next = RRIterator::edns_skip_rr(packet, offset);
+            if BigEndian::read_u16(&packet[offset + DNS_EDNS_RR_CODE_OFFSET..]) == code {
+                options.push(&packet[offset + DNS_EDNS_RR_HEADER_SIZE..offset_next]);
+            }
+            offset = offset_next;
+        }
+        options
+    }
+
+    /// Returns all the extended DNS errors, in order.
+    pub fn extended_errors(&self) -> Result<Vec<ExtendedErrorInfo>, Error> {
+        self.edns_options(EdnsOption::EDE.into())
+            .into_iter()
+            .map(ExtendedErrorInfo::decode)
+            .collect()
+    }
+
+    /// Returns the edns client subnet option, if the packet contains one.
+    pub fn client_subnet(&self) -> Result<Option<ClientSubnet>, Error> {
+        match self.edns_option(EdnsOption::CLIENT_SUBNET.into()) {
+            None => Ok(None),
+            Some(data) => ClientSubnet::decode(data).map(Some),
+        }
+    }
+
+    /// Returns the cookie option, if the packet contains one.
+    pub fn cookie(&self) -> Result<Option<Cookie>, Error> {
+        match self.edns_option(EdnsOption::COOKIE.into()) {
+            None => Ok(None),
+            Some(data) => Cookie::decode(data).map(Some),
+        }
+    }
+
+    /// Returns the TSIG record, if the packet contains one.
+    pub fn tsig(&self) -> Result<Option<TsigRecord>, Error> {
+        match self.iter_additional().last() {
+            Some(record) if record.rr_type() == Type::TSIG.into() => {
+                TsigRecord::decode(record.name(), record.rdata()).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// A read-only view of a record from a parsed packet.
+/// Questions don't have a TTL nor any data: `rr_ttl()` returns `0` and `rdata()` returns
+/// an empty slice for them.
+#[derive(Clone, Copy, Debug)]
+pub struct RecordRef<'a> {
+    packet: &'a [u8],
+    section: Section,
+    offset: usize,
+    name_end: usize,
+}
+
+impl<'a> RecordRef<'a> {
+    /// Returns the section the record belongs to.
+    #[inline]
+    pub fn section(&self) -> Section {
+        self.section
+    }
+
+    /// Returns the offset of the record in the packet.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the record name (labels are dot-delimited), converted to lower-case.
+    pub fn name(&self) -> Vec<u8> {
+        let mut name = Compress::raw_name_to_str(self.packet, self.offset);
+        name.make_ascii_lowercase();
+        name
+    }
+
+    /// Appends the uncompressed record name (raw format, with labels prefixed by their length)
+    /// to the given vector. Returns the length of the uncompressed name.
+    pub fn copy_raw_name(&self, name: &mut Vec<u8>) -> usize {
+        Compress::copy_uncompressed_name(name, self.packet, self.offset).name_len
+    }
+
+    /// Returns the record type.
+    #[inline]
+    pub fn rr_type(&self) -> u16 {
+        BigEndian::read_u16(&self.packet[self.name_end + DNS_RR_TYPE_OFFSET..])
+    }
+
+    /// Returns the record class.
+    #[inline]
+    pub fn rr_class(&self) -> u16 {
+        BigEndian::read_u16(&self.packet[self.name_end + DNS_RR_CLASS_OFFSET..])
+    }
+
+    /// Returns the record TTL.
+    #[inline]
+    pub fn rr_ttl(&self) -> u32 {
+        if self.section == Section::Question {
+            return 0;
+        }
+        BigEndian::read_u32(&self.packet[self.name_end + DNS_RR_TTL_OFFSET..])
+    }
+
+    /// Returns the location of the record, for functions operating on raw records.
+    #[inline]
+    pub(crate) fn raw(&self) -> RRRaw<'a> {
+        RRRaw {
+            packet: self.packet,
+            offset: self.offset,
+            name_end: self.name_end,
+        }
+    }
+
+    /// Returns the raw record data. Names it contains may be compressed.
+    pub fn rdata(&self) -> &'a [u8] {
+        if self.section == Section::Question {
+            return &[];
+        }
+        let offset = self.name_end + DNS_RR_HEADER_SIZE;
+        let rdlen = BigEndian::read_u16(&self.packet[self.name_end + DNS_RR_RDLEN_OFFSET..]);
+        &self.packet[offset..offset + rdlen as usize]
+    }
+}
+
+/// An iterator over the records of a section, yielding read-only `RecordRef` views.
+#[derive(Clone, Debug)]
+pub struct Records<'a> {
+    packet: &'a [u8],
+    section: Section,
+    offset: usize,
+    rrs_left: u16,
+    include_opt: bool,
+}
+
+impl<'a> Records<'a> {
+    fn new(packet: &'a [u8], section: Section, offset: Option<usize>) -> Self {
+        let rrs_left = match (offset, section) {
+            (None, _) => 0,
+            (_, Section::Question) => DNSSector::qdcount(packet),
+            (_, Section::Answer) => DNSSector::ancount(packet),
+            (_, Section::NameServers) => DNSSector::nscount(packet),
+            (_, Section::Additional) => DNSSector::arcount(packet),
+            (_, Section::Edns) => panic!("Edns options are not records"),
+        };
+        Records {
+            packet,
+            section,
+            offset: offset.unwrap_or(0),
+            rrs_left,
+            include_opt: false,
+        }
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = RecordRef<'a>;
+
+    fn next(&mut self) -> Option<RecordRef<'a>> {
+        while self.rrs_left > 0 {
+            self.rrs_left -= 1;
+            let offset = self.offset;
+            let name_end = RRIterator::skip_name(self.packet, offset);
+            let record = RecordRef {
+                packet: self.packet,
+                section: self.section,
+                offset,
+                name_end,
+            };
+            if self.section == Section::Question {
+                self.offset = name_end + DNS_RR_QUESTION_HEADER_SIZE;
+                return Some(record);
+            }
+            self.offset = RRIterator::skip_rdata(self.packet, name_end);
+            if self.section == Section::Additional
+                && !self.include_opt
+                && record.rr_type() == Type::OPT.into()
+            {
+                continue;
+            }
+            return Some(record);
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.rrs_left as usize))
+    }
+}
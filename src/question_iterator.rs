@@ -36,7 +36,12 @@ impl<'t> DNSIterable for QuestionIterator<'t> {
     }
 
     fn recompute_rr(&mut self) {
-        self.rr_iterator.recompute();
+        let rr_iterator = &mut self.rr_iterator;
+        let offset = rr_iterator
+            .offset
+            .expect("recompute() called prior to iterating over RRs");
+        rr_iterator.name_end = RRIterator::skip_name(rr_iterator.parsed_packet.packet(), offset);
+        rr_iterator.offset_next = rr_iterator.name_end + DNS_RR_QUESTION_HEADER_SIZE;
     }
 
     fn recompute_sections(&mut self) {
@@ -71,32 +76,35 @@ impl<'t> DNSIterable for QuestionIterator<'t> {
         &mut self.rr_iterator.parsed_packet
     }
 
-    fn next(mut self) -> Option<Self> {
-        {
-            let rr_iterator = &mut self.rr_iterator;
-            debug_assert_eq!(rr_iterator.section, Section::Question);
-            if rr_iterator.offset.is_none() {
-                let count = DNSSector::qdcount(&rr_iterator.parsed_packet.packet());
-                if count == 0 {
-                    return None;
-                }
-                debug_assert_eq!(count, 1);
-                rr_iterator.rrs_left = count;
-                rr_iterator.offset_next = rr_iterator.parsed_packet.offset_question.unwrap();
-            }
-            if rr_iterator.rrs_left == 0 {
-                return None;
+    fn advance(&mut self) -> bool {
+        let rr_iterator = &mut self.rr_iterator;
+        debug_assert_eq!(rr_iterator.section, Section::Question);
+        if rr_iterator.exhausted {
+            return false;
+        }
+        if rr_iterator.offset.is_none() {
+            let count = DNSSector::qdcount(&rr_iterator.parsed_packet.packet());
+            if count == 0 {
+                rr_iterator.exhausted = true;
+                return false;
             }
-            rr_iterator.rrs_left -= 1;
-            rr_iterator.offset = Some(rr_iterator.offset_next);
-            rr_iterator.name_end = RRIterator::skip_name(
-                &rr_iterator.parsed_packet.packet(),
-                rr_iterator.offset.unwrap(),
-            );
-            let offset_next = rr_iterator.name_end + DNS_RR_QUESTION_HEADER_SIZE;
-            rr_iterator.offset_next = offset_next;
+            rr_iterator.rrs_left = count;
+            rr_iterator.offset_next = rr_iterator.parsed_packet.offset_question.unwrap();
+        }
+        if rr_iterator.rrs_left == 0 {
+            rr_iterator.offset = None;
+            rr_iterator.exhausted = true;
+            return false;
         }
-        Some(self)
+        rr_iterator.rrs_left -= 1;
+        rr_iterator.offset = Some(rr_iterator.offset_next);
+        rr_iterator.name_end = RRIterator::skip_name(
+            &rr_iterator.parsed_packet.packet(),
+            rr_iterator.offset.unwrap(),
+        );
+        let offset_next = rr_iterator.name_end + DNS_RR_QUESTION_HEADER_SIZE;
+        rr_iterator.offset_next = offset_next;
+        true
     }
 }
 
@@ -0,0 +1,672 @@
+use crate::compress::*;
+use crate::constants::*;
+use crate::dns_sector::*;
+use crate::errors::*;
+use crate::parse_options::*;
+use byteorder::{BigEndian, ByteOrder};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Data of a `MX` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MxRdata {
+    pub preference: u16,
+    pub exchange: Vec<u8>,
+}
+
+/// Data of a `SOA` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SoaRdata {
+    /// Primary name server.
+    pub mname: Vec<u8>,
+    /// Mailbox of the person responsible for the zone.
+    pub rname: Vec<u8>,
+    pub serial: u32,
+    pub refresh: u32,
+    pub retry: u32,
+    pub expire: u32,
+    /// TTL of negative responses.
+    pub minimum: u32,
+}
+
+/// Data of a `SRV` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SrvRdata {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: Vec<u8>,
+}
+
+/// Data of a `NAPTR` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NaptrRdata {
+    pub order: u16,
+    pub preference: u16,
+    pub flags: Vec<u8>,
+    pub services: Vec<u8>,
+    pub regexp: Vec<u8>,
+    pub replacement: Vec<u8>,
+}
+
+/// Data of a `CAA` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CaaRdata {
+    pub flags: u8,
+    pub tag: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+/// Data of a `DS` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DsRdata {
+    pub key_tag: u16,
+    pub algorithm: u8,
+    pub digest_type: u8,
+    pub digest: Vec<u8>,
+}
+
+/// Data of a `DNSKEY` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DnskeyRdata {
+    pub flags: u16,
+    pub protocol: u8,
+    pub algorithm: u8,
+    pub public_key: Vec<u8>,
+}
+
+/// Data of a `RRSIG` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RrsigRdata {
+    pub type_covered: u16,
+    pub algorithm: u8,
+    pub labels: u8,
+    pub original_ttl: u32,
+    pub expiration: u32,
+    pub inception: u32,
+    pub key_tag: u16,
+    pub signer_name: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// Data of a `NSEC` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NsecRdata {
+    pub next_domain_name: Vec<u8>,
+    /// Types present at the owner name, in ascending order.
+    pub types: Vec<u16>,
+}
+
+/// Data of a `NSEC3` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Nsec3Rdata {
+    pub hash_algorithm: u8,
+    pub flags: u8,
+    pub iterations: u16,
+    pub salt: Vec<u8>,
+    /// Hash of the next owner name, in binary form.
+    pub next_hashed_owner_name: Vec<u8>,
+    /// Types present at the owner name, in ascending order.
+    pub types: Vec<u16>,
+}
+
+/// Data of a `TLSA` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TlsaRdata {
+    pub cert_usage: u8,
+    pub selector: u8,
+    pub matching_type: u8,
+    pub cert_data: Vec<u8>,
+}
+
+/// Data of a `SSHFP` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SshfpRdata {
+    pub algorithm: u8,
+    pub fingerprint_type: u8,
+    pub fingerprint: Vec<u8>,
+}
+
+/// Data of a `SVCB` or `HTTPS` record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SvcbRdata {
+    /// `0` for records in alias mode.
+    pub priority: u16,
+    pub target: Vec<u8>,
+    /// Service parameters, as (key, value) pairs in wire order.
+    pub params: Vec<(u16, Vec<u8>)>,
+}
+
+/// Typed data of a record.
+/// Names are dot-delimited strings, with their original case. The root name is empty.
+/// Dots and backslashes within labels are escaped as `\.` and `\\`, and other
+/// non-printable characters as `\DDD`, like in zone files.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Rdata {
+    A(Ipv4Addr),
+    AAAA(Ipv6Addr),
+    NS(Vec<u8>),
+    CNAME(Vec<u8>),
+    PTR(Vec<u8>),
+    DNAME(Vec<u8>),
+    MX(MxRdata),
+    SOA(SoaRdata),
+    /// Character strings, without their length prefix.
+    TXT(Vec<Vec<u8>>),
+    SRV(SrvRdata),
+    NAPTR(NaptrRdata),
+    CAA(CaaRdata),
+    DS(DsRdata),
+    DNSKEY(DnskeyRdata),
+    RRSIG(RrsigRdata),
+    NSEC(NsecRdata),
+    NSEC3(Nsec3Rdata),
+    TLSA(TlsaRdata),
+    SSHFP(SshfpRdata),
+    SVCB(SvcbRdata),
+    HTTPS(SvcbRdata),
+    /// Record type and raw data of any other record.
+    Unknown(u16, Vec<u8>),
+}
+
+/// Bounds-checked reader over uncompressed record data.
+struct RdataReader<'t> {
+    rdata: &'t [u8],
+    offset: usize,
+}
+
+impl<'t> RdataReader<'t> {
+    fn bytes(&mut self, len: usize) -> Result<&'t [u8], Error> {
+        if self.rdata.len() - self.offset < len {
+            bail!(DSError::PacketTooSmall);
+        }
+        let bytes = &self.rdata[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        Ok(BigEndian::read_u16(self.bytes(2)?))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(BigEndian::read_u32(self.bytes(4)?))
+    }
+
+    /// Reads a string prefixed with its length.
+    fn char_string(&mut self) -> Result<Vec<u8>, Error> {
+        let len = self.u8()? as usize;
+        Ok(self.bytes(len)?.to_vec())
+    }
+
+    fn name(&mut self) -> Result<Vec<u8>, Error> {
+        if self.offset >= self.rdata.len() {
+            bail!(DSError::PacketTooSmall);
+        }
+        let name_end = DNSSector::check_uncompressed_name(self.rdata, self.offset)?;
+        let mut name = Vec::with_capacity(DNS_MAX_HOSTNAME_LEN);
+        let mut offset = self.offset;
+        while self.rdata[offset] != 0 {
+            let label_len = self.rdata[offset] as usize;
+            if !name.is_empty() {
+                name.push(b'.');
+            }
+            for &c in &self.rdata[offset + 1..offset + 1 + label_len] {
+                match c {
+                    b'.' | b'\\' => name.extend_from_slice(&[b'\\', c]),
+                    0x21..=0x7e => name.push(c),
+                    c => name.extend_from_slice(format!("\\{:03}", c).as_bytes()),
+                }
+            }
+            offset += 1 + label_len;
+        }
+        self.offset = name_end;
+        Ok(name)
+    }
+
+    /// Reads a list of types encoded as a NSEC type bitmap.
+    fn type_bitmap(&mut self) -> Result<Vec<u16>, Error> {
+        let mut types = vec![];
+        while !self.is_empty() {
+            let window = self.u8()? as u16;
+            let bitmap_len = self.u8()? as usize;
+            if bitmap_len == 0 || bitmap_len > 32 {
+                bail!(DSError::InvalidPacket("Invalid NSEC type bitmap length"));
+            }
+            for (i, &octet) in self.bytes(bitmap_len)?.iter().enumerate() {
+                for bit in 0..8 {
+                    if octet & (0x80 >> bit) != 0 {
+                        types.push(window << 8 | (i * 8 + bit) as u16);
+                    }
+                }
+            }
+        }
+        Ok(types)
+    }
+
+    fn rest(&mut self) -> Vec<u8> {
+        let rest = self.rdata[self.offset..].to_vec();
+        self.offset = self.rdata.len();
+        rest
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset == self.rdata.len()
+    }
+
+    fn finish<T>(self, value: T) -> Result<T, Error> {
+        if !self.is_empty() {
+            bail!(DSError::InvalidPacket("Unexpected data after rdata"));
+        }
+        Ok(value)
+    }
+}
+
+/// Writer producing uncompressed record data.
+struct RdataWriter {
+    rdata: Vec<u8>,
+}
+
+impl RdataWriter {
+    fn u8(&mut self, value: u8) {
+        self.rdata.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        let mut bytes = [0u8; 2];
+        BigEndian::write_u16(&mut bytes, value);
+        self.rdata.extend_from_slice(&bytes);
+    }
+
+    fn u32(&mut self, value: u32) {
+        let mut bytes = [0u8; 4];
+        BigEndian::write_u32(&mut bytes, value);
+        self.rdata.extend_from_slice(&bytes);
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.rdata.extend_from_slice(bytes);
+    }
+
+    /// Writes a string prefixed with its length.
+    fn char_string(&mut self, s: &[u8]) -> Result<(), Error> {
+        if s.len() > 0xff {
+            bail!(DSError::InvalidPacket("Character string too long"));
+        }
+        self.u8(s.len() as u8);
+        self.bytes(s);
+        Ok(())
+    }
+
+    fn name(&mut self, name: &[u8]) -> Result<(), Error> {
+        let name = if name == b"." { &name[..0] } else { name };
+        let name_start = self.rdata.len();
+        let mut label = Vec::with_capacity(63);
+        let mut i = 0;
+        while i < name.len() {
+            let c = match name[i] {
+                b'.' => {
+                    if label.is_empty() {
+                        bail!(DSError::InvalidName("Spurious dot in a label"))
+                    }
+                    self.u8(label.len() as u8);
+                    self.bytes(&label);
+                    label.clear();
+                    i += 1;
+                    continue;
+                }
+                b'\\' => match name.get(i + 1..i + 4) {
+                    Some(digits) if digits.iter().all(u8::is_ascii_digit) => {
+                        let c = digits
+                            .iter()
+                            .fold(0u16, |acc, &digit| acc * 10 + (digit - b'0') as u16);
+                        if c > 0xff {
+                            bail!(DSError::InvalidName("Invalid escape sequence"))
+                        }
+                        i += 4;
+                        c as u8
+                    }
+                    _ => match name.get(i + 1) {
+                        Some(&c) if !c.is_ascii_digit() => {
+                            i += 2;
+                            c
+                        }
+                        _ => bail!(DSError::InvalidName("Invalid escape sequence")),
+                    },
+                },
+                c => {
+                    i += 1;
+                    c
+                }
+            };
+            if label.len() >= 63 {
+                bail!(DSError::InvalidName("Label too long"))
+            }
+            label.push(c);
+        }
+        if !label.is_empty() {
+            self.u8(label.len() as u8);
+            self.bytes(&label);
+        }
+        self.u8(0);
+        if self.rdata.len() - name_start > DNS_MAX_HOSTNAME_LEN {
+            bail!(DSError::InvalidName("Name too long"))
+        }
+        Ok(())
+    }
+
+    /// Writes a list of types as a NSEC type bitmap.
+    fn type_bitmap(&mut self, types: &[u16]) {
+        let mut types = types.to_vec();
+        types.sort_unstable();
+        types.dedup();
+        let mut types = types.into_iter().peekable();
+        while let Some(&first) = types.peek() {
+            let window = first >> 8;
+            let mut bitmap = [0u8; 32];
+            let mut bitmap_len = 0;
+            while let Some(rr_type) = types.next_if(|&rr_type| rr_type >> 8 == window) {
+                let bit = (rr_type & 0xff) as usize;
+                bitmap[bit / 8] |= 0x80 >> (bit % 8);
+                bitmap_len = bit / 8 + 1;
+            }
+            self.u8(window as u8);
+            self.u8(bitmap_len as u8);
+            self.bytes(&bitmap[..bitmap_len]);
+        }
+    }
+}
+
+impl Rdata {
+    /// Decodes the data of a record of type `rr_type`. Names must not be compressed.
+    pub fn decode(rr_type: u16, rdata: &[u8]) -> Result<Self, Error> {
+        let mut r = RdataReader { rdata, offset: 0 };
+        let decoded = match rr_type {
+            x if x == Type::A.into() => {
+                let mut ip = [0u8; 4];
+                ip.copy_from_slice(r.bytes(4)?);
+                Rdata::A(Ipv4Addr::from(ip))
+            }
+            x if x == Type::AAAA.into() => {
+                let mut ip = [0u8; 16];
+                ip.copy_from_slice(r.bytes(16)?);
+                Rdata::AAAA(Ipv6Addr::from(ip))
+            }
+            x if x == Type::NS.into() => Rdata::NS(r.name()?),
+            x if x == Type::CNAME.into() => Rdata::CNAME(r.name()?),
+            x if x == Type::PTR.into() => Rdata::PTR(r.name()?),
+            x if x == Type::DNAME.into() => Rdata::DNAME(r.name()?),
+            x if x == Type::MX.into() => Rdata::MX(MxRdata {
+                preference: r.u16()?,
+                exchange: r.name()?,
+            }),
+            x if x == Type::SOA.into() => Rdata::SOA(SoaRdata {
+                mname: r.name()?,
+                rname: r.name()?,
+                serial: r.u32()?,
+                refresh: r.u32()?,
+                retry: r.u32()?,
+                expire: r.u32()?,
+                minimum: r.u32()?,
+            }),
+            x if x == Type::TXT.into() => {
+                let mut strings = vec![];
+                while !r.is_empty() {
+                    strings.push(r.char_string()?);
+                }
+                Rdata::TXT(strings)
+            }
+            x if x == Type::SRV.into() => Rdata::SRV(SrvRdata {
+                priority: r.u16()?,
+                weight: r.u16()?,
+                port: r.u16()?,
+                target: r.name()?,
+            }),
+            x if x == Type::NAPTR.into() => Rdata::NAPTR(NaptrRdata {
+                order: r.u16()?,
+                preference: r.u16()?,
+                flags: r.char_string()?,
+                services: r.char_string()?,
+                regexp: r.char_string()?,
+                replacement: r.name()?,
+            }),
+            x if x == Type::CAA.into() => Rdata::CAA(CaaRdata {
+                flags: r.u8()?,
+                tag: r.char_string()?,
+                value: r.rest(),
+            }),
+            x if x == Type::DS.into() => Rdata::DS(DsRdata {
+                key_tag: r.u16()?,
+                algorithm: r.u8()?,
+                digest_type: r.u8()?,
+                digest: r.rest(),
+            }),
+            x if x == Type::DNSKEY.into() => Rdata::DNSKEY(DnskeyRdata {
+                flags: r.u16()?,
+                protocol: r.u8()?,
+                algorithm: r.u8()?,
+                public_key: r.rest(),
+            }),
+            x if x == Type::RRSIG.into() => Rdata::RRSIG(RrsigRdata {
+                type_covered: r.u16()?,
+                algorithm: r.u8()?,
+                labels: r.u8()?,
+                original_ttl: r.u32()?,
+                expiration: r.u32()?,
+                inception: r.u32()?,
+                key_tag: r.u16()?,
+                signer_name: r.name()?,
+                signature: r.rest(),
+            }),
+            x if x == Type::NSEC.into() => Rdata::NSEC(NsecRdata {
+                next_domain_name: r.name()?,
+                types: r.type_bitmap()?,
+            }),
+            x if x == Type::NSEC3.into() => Rdata::NSEC3(Nsec3Rdata {
+                hash_algorithm: r.u8()?,
+                flags: r.u8()?,
+                iterations: r.u16()?,
+                salt: r.char_string()?,
+                next_hashed_owner_name: r.char_string()?,
+                types: r.type_bitmap()?,
+            }),
+            x if x == Type::TLSA.into() => Rdata::TLSA(TlsaRdata {
+                cert_usage: r.u8()?,
+                selector: r.u8()?,
+                matching_type: r.u8()?,
+                cert_data: r.rest(),
+            }),
+            x if x == Type::SSHFP.into() => Rdata::SSHFP(SshfpRdata {
+                algorithm: r.u8()?,
+                fingerprint_type: r.u8()?,
+                fingerprint: r.rest(),
+            }),
+            x if x == Type::SVCB.into() || x == Type::HTTPS.into() => {
+                let priority = r.u16()?;
+                let target = r.name()?;
+                let mut params = vec![];
+                while !r.is_empty() {
+                    let key = r.u16()?;
+                    let len = r.u16()? as usize;
+                    params.push((key, r.bytes(len)?.to_vec()));
+                }
+                let svcb = SvcbRdata {
+                    priority,
+                    target,
+                    params,
+                };
+                if x == Type::SVCB.into() {
+                    Rdata::SVCB(svcb)
+                } else {
+                    Rdata::HTTPS(svcb)
+                }
+            }
+            _ => Rdata::Unknown(rr_type, r.rest()),
+        };
+        r.finish(decoded)
+    }
+
+    /// Encodes the record data, with uncompressed names.
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut w = RdataWriter { rdata: vec![] };
+        match self {
+            Rdata::A(ip) => w.bytes(&ip.octets()),
+            Rdata::AAAA(ip) => w.bytes(&ip.octets()),
+            Rdata::NS(name) | Rdata::CNAME(name) | Rdata::PTR(name) | Rdata::DNAME(name) => {
+                w.name(name)?
+            }
+            Rdata::MX(mx) => {
+                w.u16(mx.preference);
+                w.name(&mx.exchange)?;
+            }
+            Rdata::SOA(soa) => {
+                w.name(&soa.mname)?;
+                w.name(&soa.rname)?;
+                w.u32(soa.serial);
+                w.u32(soa.refresh);
+                w.u32(soa.retry);
+                w.u32(soa.expire);
+                w.u32(soa.minimum);
+            }
+            Rdata::TXT(strings) => {
+                for s in strings {
+                    w.char_string(s)?;
+                }
+            }
+            Rdata::SRV(srv) => {
+                w.u16(srv.priority);
+                w.u16(srv.weight);
+                w.u16(srv.port);
+                w.name(&srv.target)?;
+            }
+            Rdata::NAPTR(naptr) => {
+                w.u16(naptr.order);
+                w.u16(naptr.preference);
+                w.char_string(&naptr.flags)?;
+                w.char_string(&naptr.services)?;
+                w.char_string(&naptr.regexp)?;
+                w.name(&naptr.replacement)?;
+            }
+            Rdata::CAA(caa) => {
+                w.u8(caa.flags);
+                w.char_string(&caa.tag)?;
+                w.bytes(&caa.value);
+            }
+            Rdata::DS(ds) => {
+                w.u16(ds.key_tag);
+                w.u8(ds.algorithm);
+                w.u8(ds.digest_type);
+                w.bytes(&ds.digest);
+            }
+            Rdata::DNSKEY(dnskey) => {
+                w.u16(dnskey.flags);
+                w.u8(dnskey.protocol);
+                w.u8(dnskey.algorithm);
+                w.bytes(&dnskey.public_key);
+            }
+            Rdata::RRSIG(rrsig) => {
+                w.u16(rrsig.type_covered);
+                w.u8(rrsig.algorithm);
+                w.u8(rrsig.labels);
+                w.u32(rrsig.original_ttl);
+                w.u32(rrsig.expiration);
+                w.u32(rrsig.inception);
+                w.u16(rrsig.key_tag);
+                w.name(&rrsig.signer_name)?;
+                w.bytes(&rrsig.signature);
+            }
+            Rdata::NSEC(nsec) => {
+                w.name(&nsec.next_domain_name)?;
+                w.type_bitmap(&nsec.types);
+            }
+            Rdata::NSEC3(nsec3) => {
+                w.u8(nsec3.hash_algorithm);
+                w.u8(nsec3.flags);
+                w.u16(nsec3.iterations);
+                w.char_string(&nsec3.salt)?;
+                w.char_string(&nsec3.next_hashed_owner_name)?;
+                w.type_bitmap(&nsec3.types);
+            }
+            Rdata::TLSA(tlsa) => {
+                w.u8(tlsa.cert_usage);
+                w.u8(tlsa.selector);
+                w.u8(tlsa.matching_type);
+                w.bytes(&tlsa.cert_data);
+            }
+            Rdata::SSHFP(sshfp) => {
+                w.u8(sshfp.algorithm);
+                w.u8(sshfp.fingerprint_type);
+                w.bytes(&sshfp.fingerprint);
+            }
+            Rdata::SVCB(svcb) | Rdata::HTTPS(svcb) => {
+                w.u16(svcb.priority);
+                w.name(&svcb.target)?;
+                for (key, value) in &svcb.params {
+                    if value.len() > 0xffff {
+                        bail!(DSError::InvalidPacket("Service parameter too long"));
+                    }
+                    w.u16(*key);
+                    w.u16(value.len() as u16);
+                    w.bytes(value);
+                }
+            }
+            Rdata::Unknown(_, rdata) => w.bytes(rdata),
+        }
+        if w.rdata.len() > 0xffff {
+            bail!(DSError::InvalidPacket("RDATA too long"));
+        }
+        Ok(w.rdata)
+    }
+
+    /// Checks untrusted data for a record of type `rr_type`, as if it was found in
+    /// a packet to be parsed. Names must not be compressed, since the data is meant
+    /// to be inserted into another packet.
+    pub(crate) fn check(rr_type: u16, rr_class: u16, rdata: &[u8]) -> Result<(), Error> {
+        let mut packet = vec![0u8; DNS_HEADER_SIZE];
+        BigEndian::write_u16(&mut packet[DNS_FLAGS_OFFSET..], DNS_FLAG_QR as u16);
+        DNSSector::set_ancount(&mut packet, 1);
+        let mut header = [0u8; 1 + DNS_RR_HEADER_SIZE];
+        BigEndian::write_u16(&mut header[1 + DNS_RR_TYPE_OFFSET..], rr_type);
+        BigEndian::write_u16(&mut header[1 + DNS_RR_CLASS_OFFSET..], rr_class);
+        BigEndian::write_u16(&mut header[1 + DNS_RR_RDLEN_OFFSET..], rdata.len() as u16);
+        packet.extend_from_slice(&header);
+        packet.extend_from_slice(rdata);
+        let options = ParseOptions::new().allow_no_question(true);
+        DNSSector::from_slice(&packet).parse_ref_with(&options)?;
+        if Compress::uncompress(&packet)? != packet {
+            bail!(DSError::InvalidName("Unexpected compression"));
+        }
+        Ok(())
+    }
+
+    /// Returns the type of the record the data belongs to.
+    pub fn rr_type(&self) -> u16 {
+        match self {
+            Rdata::A(_) => Type::A.into(),
+            Rdata::AAAA(_) => Type::AAAA.into(),
+            Rdata::NS(_) => Type::NS.into(),
+            Rdata::CNAME(_) => Type::CNAME.into(),
+            Rdata::PTR(_) => Type::PTR.into(),
+            Rdata::DNAME(_) => Type::DNAME.into(),
+            Rdata::MX(_) => Type::MX.into(),
+            Rdata::SOA(_) => Type::SOA.into(),
+            Rdata::TXT(_) => Type::TXT.into(),
+            Rdata::SRV(_) => Type::SRV.into(),
+            Rdata::NAPTR(_) => Type::NAPTR.into(),
+            Rdata::CAA(_) => Type::CAA.into(),
+            Rdata::DS(_) => Type::DS.into(),
+            Rdata::DNSKEY(_) => Type::DNSKEY.into(),
+            Rdata::RRSIG(_) => Type::RRSIG.into(),
+            Rdata::NSEC(_) => Type::NSEC.into(),
+            Rdata::NSEC3(_) => Type::NSEC3.into(),
+            Rdata::TLSA(_) => Type::TLSA.into(),
+            Rdata::SSHFP(_) => Type::SSHFP.into(),
+            Rdata::SVCB(_) => Type::SVCB.into(),
+            Rdata::HTTPS(_) => Type::HTTPS.into(),
+            Rdata::Unknown(rr_type, _) => *rr_type,
+        }
+    }
+}
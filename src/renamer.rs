@@ -114,148 +114,171 @@ impl Renamer {
         source_name: &[u8],
         match_suffix: bool,
     ) -> Result<(), Error> {
-        let mut it = parsed_packet.into_iter_question();
-        while let Some(item) = it {
-            {
-                let raw = item.raw();
-                Self::copy_with_replaced_name(
-                    &mut renamed_packet,
-                    &raw.packet,
-                    raw.offset,
-                    &mut suffix_dict,
-                    &target_name,
-                    &source_name,
-                    match_suffix,
-                )?;
-                if raw.packet.len() < raw.name_end + DNS_RR_QUESTION_HEADER_SIZE {
-                    bail!(DSError::PacketTooSmall)
-                }
-                renamed_packet
-                    .extend(&raw.packet[raw.name_end..raw.name_end + DNS_RR_QUESTION_HEADER_SIZE]);
+        let mut it = parsed_packet.cursor_question();
+        while it.advance() {
+            let raw = it.raw();
+            Self::copy_with_replaced_name(
+                &mut renamed_packet,
+                &raw.packet,
+                raw.offset,
+                &mut suffix_dict,
+                &target_name,
+                &source_name,
+                match_suffix,
+            )?;
+            if raw.packet.len() < raw.name_end + DNS_RR_QUESTION_HEADER_SIZE {
+                bail!(DSError::PacketTooSmall)
             }
-            it = item.next();
+            renamed_packet
+                .extend(&raw.packet[raw.name_end..raw.name_end + DNS_RR_QUESTION_HEADER_SIZE]);
         }
         Ok(())
     }
 
     fn rename_response_section(
-        mut it: Option<ResponseIterator<'_>>,
+        mut it: ResponseIterator<'_>,
         mut renamed_packet: &mut Vec<u8>,
         mut suffix_dict: &mut SuffixDict,
         target_name: &[u8],
         source_name: &[u8],
         match_suffix: bool,
     ) -> Result<(), Error> {
-        while let Some(item) = it {
-            {
-                let raw = item.raw();
-                Self::copy_with_replaced_name(
-                    &mut renamed_packet,
-                    &raw.packet,
-                    raw.offset,
-                    &mut suffix_dict,
-                    &target_name,
-                    &source_name,
-                    match_suffix,
-                )?;
-                if raw.packet.len() < raw.name_end + DNS_RR_HEADER_SIZE {
-                    bail!(DSError::PacketTooSmall)
-                }
-                let renamed_packet_offset_data = renamed_packet.len();
-                renamed_packet.extend(&raw.packet[raw.name_end..raw.name_end + DNS_RR_HEADER_SIZE]);
-                let rr_type = item.rr_type();
-                match rr_type {
-                    x if x == Type::NS.into()
-                        || x == Type::CNAME.into()
-                        || x == Type::PTR.into() =>
-                    {
-                        let offset_rdata = raw.name_end;
-                        Self::copy_with_replaced_name(
-                            &mut renamed_packet,
-                            &raw.packet,
-                            offset_rdata + DNS_RR_HEADER_SIZE,
-                            &mut suffix_dict,
-                            &target_name,
-                            &source_name,
-                            match_suffix,
-                        )?;
-                        let new_rdlen =
-                            renamed_packet.len() - renamed_packet_offset_data - DNS_RR_HEADER_SIZE;
-                        BigEndian::write_u16(
-                            &mut renamed_packet[renamed_packet_offset_data + DNS_RR_RDLEN_OFFSET..],
-                            new_rdlen as u16,
-                        );
-                    }
-                    x if x == Type::MX.into() => {
-                        let offset_rdata = raw.name_end;
-                        renamed_packet.extend(
-                            &raw.packet[offset_rdata + DNS_RR_HEADER_SIZE
-                                ..offset_rdata + DNS_RR_HEADER_SIZE + 2],
-                        );
-                        let renamed_packet_name_offset = renamed_packet.len();
-                        Self::copy_with_replaced_name(
-                            &mut renamed_packet,
-                            &raw.packet,
-                            offset_rdata + DNS_RR_HEADER_SIZE + 2,
-                            &mut suffix_dict,
-                            &target_name,
-                            &source_name,
-                            match_suffix,
-                        )?;
-                        let new_rdlen = 2 + renamed_packet.len()
-                            - renamed_packet_name_offset
-                            - DNS_RR_HEADER_SIZE;
-                        BigEndian::write_u16(
-                            &mut renamed_packet[renamed_packet_offset_data + DNS_RR_RDLEN_OFFSET..],
-                            new_rdlen as u16,
-                        );
-                    }
-                    x if x == Type::SOA.into() => {
-                        let offset_rdata = raw.name_end;
-                        let renamed_packet_name1_offset = renamed_packet.len();
-                        let name1_offset = offset_rdata + DNS_RR_HEADER_SIZE;
-                        let name1_len = Compress::raw_name_len(&raw.packet[name1_offset..]);
-                        Self::copy_with_replaced_name(
-                            &mut renamed_packet,
-                            &raw.packet,
-                            name1_offset,
-                            &mut suffix_dict,
-                            &target_name,
-                            &source_name,
-                            match_suffix,
-                        )?;
-                        let name2_offset = name1_offset + name1_len;
-                        let name2_len = Compress::raw_name_len(&raw.packet[name2_offset..]);
-                        Self::copy_with_replaced_name(
-                            &mut renamed_packet,
-                            &raw.packet,
-                            name2_offset,
-                            &mut suffix_dict,
-                            &target_name,
-                            &source_name,
-                            match_suffix,
-                        )?;
-                        let soa_metadata_offset = name2_offset + name2_len;
-                        renamed_packet
-                            .extend(&raw.packet[soa_metadata_offset..soa_metadata_offset + 20]);
-                        let new_rdlen =
-                            renamed_packet.len() - renamed_packet_name1_offset - DNS_RR_HEADER_SIZE;
-                        BigEndian::write_u16(
-                            &mut renamed_packet[renamed_packet_offset_data + DNS_RR_RDLEN_OFFSET..],
-                            new_rdlen as u16,
-                        );
-                    }
-                    _ => {
-                        let rd_len = item.rr_rdlen();
-                        let packet = &raw.packet;
-                        let offset_rdata = raw.name_end;
-                        let rdata =
-                            &packet[offset_rdata..offset_rdata + DNS_RR_HEADER_SIZE + rd_len];
-                        renamed_packet.extend(&rdata[DNS_RR_HEADER_SIZE..]);
-                    }
-                };
+        while it.advance() {
+            let raw = it.raw();
+            Self::copy_with_replaced_name(
+                &mut renamed_packet,
+                &raw.packet,
+                raw.offset,
+                &mut suffix_dict,
+                &target_name,
+                &source_name,
+                match_suffix,
+            )?;
+            if raw.packet.len() < raw.name_end + DNS_RR_HEADER_SIZE {
+                bail!(DSError::PacketTooSmall)
             }
-            it = item.next();
+            let renamed_packet_offset_data = renamed_packet.len();
+            renamed_packet.extend(&raw.packet[raw.name_end..raw.name_end + DNS_RR_HEADER_SIZE]);
+            let rr_type = it.rr_type();
+            match rr_type {
+                x if x == Type::NS.into() || x == Type::CNAME.into() || x == Type::PTR.into() => {
+                    let offset_rdata = raw.name_end;
+                    Self::copy_with_replaced_name(
+                        &mut renamed_packet,
+                        &raw.packet,
+                        offset_rdata + DNS_RR_HEADER_SIZE,
+                        &mut suffix_dict,
+                        &target_name,
+                        &source_name,
+                        match_suffix,
+                    )?;
+                    let new_rdlen =
+                        renamed_packet.len() - renamed_packet_offset_data - DNS_RR_HEADER_SIZE;
+                    BigEndian::write_u16(
+                        &mut renamed_packet[renamed_packet_offset_data + DNS_RR_RDLEN_OFFSET..],
+                        new_rdlen as u16,
+                    );
+                }
+                x if x == Type::MX.into() => {
+                    let offset_rdata = raw.name_end;
+                    renamed_packet.extend(
+                        &raw.packet[offset_rdata + DNS_RR_HEADER_SIZE
+                            ..offset_rdata + DNS_RR_HEADER_SIZE + 2],
+                    );
+                    let renamed_packet_name_offset = renamed_packet.len();
+                    Self::copy_with_replaced_name(
+                        &mut renamed_packet,
+                        &raw.packet,
+                        offset_rdata + DNS_RR_HEADER_SIZE + 2,
+                        &mut suffix_dict,
+                        &target_name,
+                        &source_name,
+                        match_suffix,
+                    )?;
+                    let new_rdlen =
+                        2 + renamed_packet.len() - renamed_packet_name_offset - DNS_RR_HEADER_SIZE;
+                    BigEndian::write_u16(
+                        &mut renamed_packet[renamed_packet_offset_data + DNS_RR_RDLEN_OFFSET..],
+                        new_rdlen as u16,
+                    );
+                }
+                x if x == Type::SOA.into() => {
+                    let offset_rdata = raw.name_end;
+                    let renamed_packet_name1_offset = renamed_packet.len();
+                    let name1_offset = offset_rdata + DNS_RR_HEADER_SIZE;
+                    let name1_len = Compress::raw_name_len(&raw.packet[name1_offset..]);
+                    Self::copy_with_replaced_name(
+                        &mut renamed_packet,
+                        &raw.packet,
+                        name1_offset,
+                        &mut suffix_dict,
+                        &target_name,
+                        &source_name,
+                        match_suffix,
+                    )?;
+                    let name2_offset = name1_offset + name1_len;
+                    let name2_len = Compress::raw_name_len(&raw.packet[name2_offset..]);
+                    Self::copy_with_replaced_name(
+                        &mut renamed_packet,
+                        &raw.packet,
+                        name2_offset,
+                        &mut suffix_dict,
+                        &target_name,
+                        &source_name,
+                        match_suffix,
+                    )?;
+                    let soa_metadata_offset = name2_offset + name2_len;
+                    renamed_packet
+                        .extend(&raw.packet[soa_metadata_offset..soa_metadata_offset + 20]);
+                    let new_rdlen =
+                        renamed_packet.len() - renamed_packet_name1_offset - DNS_RR_HEADER_SIZE;
+                    BigEndian::write_u16(
+                        &mut renamed_packet[renamed_packet_offset_data + DNS_RR_RDLEN_OFFSET..],
+                        new_rdlen as u16,
+                    );
+                }
+                x if x == Type::MINFO.into() => {
+                    let offset_rdata = raw.name_end;
+                    let renamed_packet_name1_offset = renamed_packet.len();
+                    let name1_offset = offset_rdata + DNS_RR_HEADER_SIZE;
+                    let name1_len = Compress::raw_name_len(&raw.packet[name1_offset..]);
+                    Self::copy_with_replaced_name(
+                        &mut renamed_packet,
+                        &raw.packet,
+                        name1_offset,
+                        &mut suffix_dict,
+                        &target_name,
+                        &source_name,
+                        match_suffix,
+                    )?;
+                    let name2_offset = name1_offset + name1_len;
+                    Self::copy_with_replaced_name(
+                        &mut renamed_packet,
+                        &raw.packet,
+                        name2_offset,
+                        &mut suffix_dict,
+                        &target_name,
+                        &source_name,
+                        match_suffix,
+                    )?;
+                    let new_rdlen = renamed_packet.len() - renamed_packet_name1_offset;
+                    BigEndian::write_u16(
+                        &mut renamed_packet[renamed_packet_offset_data + DNS_RR_RDLEN_OFFSET..],
+                        new_rdlen as u16,
+                    );
+                }
+                x if Compress::rfc3597_rdata_names(x).is_some() || x == Type::NAPTR.into() => {
+                    renamed_packet.truncate(renamed_packet_offset_data);
+                    Compress::uncompress_rdata(renamed_packet, raw, Some(x), Some(it.rr_rdlen()));
+                }
+                _ => {
+                    let rd_len = it.rr_rdlen();
+                    let packet = &raw.packet;
+                    let offset_rdata = raw.name_end;
+                    let rdata = &packet[offset_rdata..offset_rdata + DNS_RR_HEADER_SIZE + rd_len];
+                    renamed_packet.extend(&rdata[DNS_RR_HEADER_SIZE..]);
+                }
+            };
         }
         Ok(())
     }
@@ -268,7 +291,7 @@ impl Renamer {
         source_name: &[u8],
         match_suffix: bool,
     ) -> Result<(), Error> {
-        let it = parsed_packet.into_iter_answer() as Option<ResponseIterator<'_>>;
+        let it = parsed_packet.cursor_answer();
         Self::rename_response_section(
             it,
             renamed_packet,
@@ -287,7 +310,7 @@ impl Renamer {
         source_name: &[u8],
         match_suffix: bool,
     ) -> Result<(), Error> {
-        let it = parsed_packet.into_iter_nameservers() as Option<ResponseIterator<'_>>;
+        let it = parsed_packet.cursor_nameservers();
         Self::rename_response_section(
             it,
             renamed_packet,
@@ -306,7 +329,7 @@ impl Renamer {
         source_name: &[u8],
         match_suffix: bool,
     ) -> Result<(), Error> {
-        let it = parsed_packet.into_iter_additional() as Option<ResponseIterator<'_>>;
+        let it = parsed_packet.cursor_additional();
         Self::rename_response_section(
             it,
             renamed_packet,
@@ -76,9 +76,13 @@ impl<'t> DNSIterable for ResponseIterator<'t> {
         &mut self.rr_iterator.parsed_packet
     }
 
-    fn next(self) -> Option<Self> {
-        self.next_including_opt()
-            .and_then(move |this| this.maybe_skip_opt_section())
+    fn advance(&mut self) -> bool {
+        while self.advance_including_opt() {
+            if self.rr_type() != Type::OPT.into() {
+                return true;
+            }
+        }
+        false
     }
 }
 
@@ -87,60 +91,57 @@ impl<'t> ResponseIterator<'t> {
         ResponseIterator { rr_iterator }
     }
 
+    /// Returns the next record, or `None` if there aren't any left - OPT RRs are included.
     pub fn next_including_opt(mut self) -> Option<Self> {
-        {
-            let rr_iterator = &mut self.rr_iterator;
-            let parsed_packet = &mut rr_iterator.parsed_packet;
-            if rr_iterator.offset.is_none() {
-                let (count, offset) = match rr_iterator.section {
-                    Section::Answer => (
-                        DNSSector::ancount(&parsed_packet.packet()),
-                        parsed_packet.offset_answers,
-                    ),
-                    Section::NameServers => (
-                        DNSSector::nscount(&parsed_packet.packet()),
-                        parsed_packet.offset_nameservers,
-                    ),
-                    Section::Additional => (
-                        DNSSector::arcount(&parsed_packet.packet()),
-                        parsed_packet.offset_additional,
-                    ),
-                    _ => unreachable!("Unexpected section"),
-                };
-                if count == 0 {
-                    return None;
-                }
-                rr_iterator.rrs_left = count;
-                rr_iterator.offset_next = offset.unwrap();
-            }
-            if rr_iterator.rrs_left == 0 {
-                return None;
-            }
-            rr_iterator.rrs_left -= 1;
-            rr_iterator.offset = Some(rr_iterator.offset_next);
-            rr_iterator.name_end =
-                RRIterator::skip_name(&parsed_packet.packet(), rr_iterator.offset.unwrap());
-            let offset_next = RRIterator::skip_rdata(&parsed_packet.packet(), rr_iterator.name_end);
-            rr_iterator.offset_next = offset_next;
+        if self.advance_including_opt() {
+            Some(self)
+        } else {
+            None
         }
-        Some(self)
     }
 
-    fn maybe_skip_opt_section(mut self) -> Option<Self> {
-        if self.rr_type() == Type::OPT.into() {
-            let rr_iterator = &mut self.rr_iterator;
-            debug_assert_eq!(rr_iterator.section, Section::Additional);
-            let parsed_packet = &mut rr_iterator.parsed_packet;
-            if rr_iterator.rrs_left == 0 {
-                return None;
+    /// Moves to the next record, and returns `false` if there aren't any left - OPT RRs
+    /// are included. See `DNSIterable::advance()`.
+    pub fn advance_including_opt(&mut self) -> bool {
+        let rr_iterator = &mut self.rr_iterator;
+        let parsed_packet = &mut rr_iterator.parsed_packet;
+        if rr_iterator.exhausted {
+            return false;
+        }
+        if rr_iterator.offset.is_none() {
+            let (count, offset) = match rr_iterator.section {
+                Section::Answer => (
+                    DNSSector::ancount(&parsed_packet.packet()),
+                    parsed_packet.offset_answers,
+                ),
+                Section::NameServers => (
+                    DNSSector::nscount(&parsed_packet.packet()),
+                    parsed_packet.offset_nameservers,
+                ),
+                Section::Additional => (
+                    DNSSector::arcount(&parsed_packet.packet()),
+                    parsed_packet.offset_additional,
+                ),
+                _ => unreachable!("Unexpected section"),
+            };
+            if count == 0 {
+                rr_iterator.exhausted = true;
+                return false;
             }
-            rr_iterator.offset = Some(rr_iterator.offset_next);
-            rr_iterator.name_end =
-                RRIterator::skip_name(&parsed_packet.packet(), rr_iterator.offset.unwrap());
-            let offset_next = RRIterator::skip_rdata(&parsed_packet.packet(), rr_iterator.name_end);
-            rr_iterator.offset_next = offset_next;
+            rr_iterator.rrs_left = count;
+            rr_iterator.offset_next = offset.unwrap();
+        }
+        if rr_iterator.rrs_left == 0 {
+            rr_iterator.offset = None;
+            rr_iterator.exhausted = true;
+            return false;
         }
-        debug_assert!(self.rr_type() != Type::OPT.into());
-        Some(self)
+        rr_iterator.rrs_left -= 1;
+        rr_iterator.offset = Some(rr_iterator.offset_next);
+        rr_iterator.name_end =
+            RRIterator::skip_name(&parsed_packet.packet(), rr_iterator.offset.unwrap());
+        let offset_next = RRIterator::skip_rdata(&parsed_packet.packet(), rr_iterator.name_end);
+        rr_iterator.offset_next = offset_next;
+        true
     }
 }
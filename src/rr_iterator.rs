@@ -3,6 +3,7 @@ use crate::constants::*;
 use crate::dns_sector::*;
 use crate::errors::*;
 use crate::parsed_packet::*;
+use crate::rdata::*;
 use byteorder::{BigEndian, ByteOrder};
 use std::marker;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
@@ -28,10 +29,22 @@ pub struct RRRawMut<'t> {
 
 /// The `DNSIterable` trait represents a set of records that can be iterated over.
 pub trait DNSIterable {
+    /// Moves to the next record, and returns `false` if there aren't any left.
+    /// The iterator then doesn't point to a valid record any more, and calling `advance()`
+    /// again keeps returning `false`.
+    fn advance(&mut self) -> bool;
+
     /// Returns the next record, or `None` if there aren't any left.
-    fn next(self) -> Option<Self>
+    fn next(mut self) -> Option<Self>
     where
-        Self: marker::Sized;
+        Self: marker::Sized,
+    {
+        if self.advance() {
+            Some(self)
+        } else {
+            None
+        }
+    }
 
     /// Returns the offset of the current RR, or `None` if we haven't started iterating yet
     /// or if the current record has been deleted.
@@ -194,11 +207,12 @@ pub trait TypedIterable {
                 return Ok(());
             }
             let offset = self.offset().ok_or(DSError::VoidRecord)?;
+            let max_packet_size = self.parsed_packet().max_packet_size;
             let packet = &mut self.parsed_packet_mut().packet_mut();
             let packet_len = packet.len();
             if shift > 0 {
                 let new_packet_len = packet_len + shift as usize;
-                if new_packet_len > 0xffff {
+                if new_packet_len > max_packet_size {
                     bail!(DSError::PacketTooLarge);
                 }
                 packet.resize(new_packet_len, 0);
@@ -206,7 +220,7 @@ pub trait TypedIterable {
                     new_packet_len,
                     (offset as isize + shift) as usize + (packet_len - offset) as usize
                 );
-                packet.copy_within(offset..offset + packet_len, offset + shift as usize);
+                packet.copy_within(offset..packet_len, offset + shift as usize);
             } else if shift < 0 {
                 let shift = (-shift) as usize;
                 assert!(packet_len >= shift);
@@ -217,7 +231,15 @@ pub trait TypedIterable {
         let new_offset_next = (self.offset_next() as isize + shift) as usize;
         self.set_offset_next(new_offset_next);
         let section = self.current_section()?;
+        let offset = self.offset().ok_or(DSError::VoidRecord)?;
         let parsed_packet = self.parsed_packet_mut();
+        parsed_packet.offset_edns = parsed_packet.offset_edns.map(|x| {
+            if x > offset {
+                (x as isize + shift) as usize
+            } else {
+                x
+            }
+        });
         if section == Section::NameServers
             || section == Section::Answer
             || section == Section::Question
@@ -267,6 +289,9 @@ pub trait TypedIterable {
             packet[offset..offset + new_name_len].copy_from_slice(name);
         }
         self.recompute_rr();
+        if self.current_section()? == Section::Question {
+            self.parsed_packet_mut().cached = None;
+        }
 
         Ok(())
     }
@@ -299,6 +324,9 @@ pub trait TypedIterable {
         self.set_offset_next(offset);
         self.invalidate();
         let parsed_packet = self.parsed_packet_mut();
+        if section == Section::Question {
+            parsed_packet.cached = None;
+        }
         let rrcount = parsed_packet.rrcount_dec(section)?;
         if rrcount <= 0 {
             let offset = match section {
@@ -411,6 +439,69 @@ pub trait RdataIterable {
             _ => bail!(DSError::PropertyNotFound),
         }
     }
+
+    /// Decodes the data of the current RR. Compressed names are resolved.
+    fn rdata(&self) -> Result<Rdata, Error>
+    where
+        Self: DNSIterable + TypedIterable,
+    {
+        let rr_type = self.rr_type();
+        let mut uncompressed = Vec::with_capacity(DNS_RR_HEADER_SIZE + self.rr_rdlen());
+        Compress::uncompress_rdata(
+            &mut uncompressed,
+            self.raw(),
+            Some(rr_type),
+            Some(self.rr_rdlen()),
+        );
+        Rdata::decode(rr_type, &uncompressed[DNS_RR_HEADER_SIZE..])
+    }
+
+    /// Replaces the data of the current RR. The type of `rdata` must match the type of the record.
+    /// OPT and TSIG records cannot be changed using this function.
+    fn set_rdata(&mut self, rdata: &Rdata) -> Result<(), Error>
+    where
+        Self: DNSIterable + TypedIterable,
+    {
+        self.offset().ok_or(DSError::VoidRecord)?;
+        let rr_type = self.rr_type();
+        if rdata.rr_type() != rr_type {
+            bail!(DSError::InvalidPacket(
+                "Record data doesn't match the record type"
+            ));
+        }
+        if rr_type == Type::OPT.into() || rr_type == Type::TSIG.into() {
+            bail!(DSError::PropertyNotFound);
+        }
+        let new_rdata = rdata.encode()?;
+        if let Rdata::Unknown(..) = rdata {
+            Rdata::check(rr_type, self.rr_class(), &new_rdata)?;
+        }
+        if self.parsed_packet().maybe_compressed {
+            let (uncompressed, new_offset) = {
+                let ref_offset = self.offset().ok_or(DSError::VoidRecord)?;
+                let compressed = self.raw_mut().packet;
+                Compress::uncompress_with_previous_offset(compressed, ref_offset)?
+            };
+            self.parsed_packet_mut().packet = Some(uncompressed);
+            self.set_offset(new_offset);
+            self.recompute_rr();
+            self.recompute_sections();
+        }
+        let offset = self.offset().ok_or(DSError::VoidRecord)?;
+        debug_assert!(!self.parsed_packet().maybe_compressed);
+        let mut rr = self.packet()[offset..self.raw().name_end + DNS_RR_HEADER_SIZE].to_vec();
+        let rdlen_offset = rr.len() - DNS_RR_HEADER_SIZE + DNS_RR_RDLEN_OFFSET;
+        BigEndian::write_u16(&mut rr[rdlen_offset..], new_rdata.len() as u16);
+        rr.extend_from_slice(&new_rdata);
+        let shift = new_rdata.len() as isize - self.rr_rdlen() as isize;
+        self.resize_rr(shift)?;
+        {
+            let packet = self.parsed_packet_mut().packet_mut();
+            packet[offset..offset + rr.len()].copy_from_slice(&rr);
+        }
+        self.recompute_rr();
+        Ok(())
+    }
 }
 
 /// An `RRIterator` structure is a generic way to iterate over the records
@@ -428,6 +519,7 @@ pub struct RRIterator<'t> {
     pub offset_next: usize,
     pub name_end: usize,
     pub rrs_left: u16,
+    pub exhausted: bool,
 }
 
 impl<'t> RRIterator<'t> {
@@ -440,6 +532,7 @@ impl<'t> RRIterator<'t> {
             offset_next: 0,
             name_end: 0,
             rrs_left: 0,
+            exhausted: false,
         }
     }
 
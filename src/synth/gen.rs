@@ -124,6 +124,21 @@ impl RR {
         })
     }
 
+    /// Creates a question from a raw name, that must be valid and uncompressed.
+    pub fn new_question_from_raw_name(raw_name: &[u8], rr_type: u16, class: u16) -> Self {
+        let mut packet = Vec::with_capacity(raw_name.len() + DNS_RR_QUESTION_HEADER_SIZE);
+        packet.extend_from_slice(raw_name);
+        let mut header = [0u8; DNS_RR_QUESTION_HEADER_SIZE];
+        BigEndian::write_u16(&mut header[DNS_RR_TYPE_OFFSET..], rr_type);
+        BigEndian::write_u16(&mut header[DNS_RR_CLASS_OFFSET..], class);
+        packet.extend_from_slice(&header);
+        let rdata_offset = packet.len() as u16;
+        RR {
+            packet,
+            rdata_offset,
+        }
+    }
+
     pub fn from_string(s: &str) -> Result<RR, Error> {
         match parse_only(rr_parser, s.as_bytes()) {
             Err(_) => bail!(DSError::ParseError),
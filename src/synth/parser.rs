@@ -179,12 +179,16 @@ fn ttl_parser<I: U8Input>(i: I) -> SimpleResult<I, u32> {
     }
 }
 
-fn class_parser<I: U8Input>(i: I) -> SimpleResult<I, ()> {
-    parse! {i;
-        satisfy(|c| c == b'I' || c == b'i');
-        satisfy(|c| c == b'N' || c == b'n');
-        ret ()
-    }
+fn class_parser<I: U8Input>(i: I) -> SimpleResult<I, Class> {
+    take_while1(i, is_alphanumeric).bind(|i, it| {
+        match str::from_utf8(&it.into_vec())
+            .ok()
+            .and_then(|s| Class::from_string(s).ok())
+        {
+            Some(class) => i.ret(class),
+            None => i.err(parsers::Error::unexpected()),
+        }
+    })
 }
 
 pub fn rr_type_parser<I: U8Input>(i: I) -> SimpleResult<I, Type> {
@@ -279,14 +283,13 @@ fn addr_arpa_parser<I: U8Input>(i: I) -> SimpleResult<I, Vec<u8>> {
 }
 
 fn rr_common_parser<I: U8Input>(i: I) -> SimpleResult<I, RRHeader> {
-    let class = Class::IN;
     parse! {i;
         maybe_skip_horizontal_whitespaces();
         let name = hostname_parser();
         maybe_skip_horizontal_whitespaces();
         let ttl: u32 = ttl_parser();
         maybe_skip_horizontal_whitespaces();
-        class_parser();
+        let class = class_parser();
         skip_horizontal_whitespaces();
         let rr_type = rr_type_parser();
         ret RRHeader {
@@ -0,0 +1,88 @@
+use crate::constants::*;
+use crate::dns_sector::*;
+use crate::errors::*;
+use crate::parse_options::*;
+use crate::parsed_packet::*;
+use byteorder::{BigEndian, ByteOrder};
+use std::io::Read;
+
+/// Reassembles DNS packets from a TCP or TLS byte stream, where every packet is
+/// prefixed with its length, encoded as a 16-bit big-endian integer.
+/// Data can be pushed in arbitrary chunks; a chunk may contain a partial packet,
+/// or several pipelined packets.
+#[derive(Clone, Debug)]
+pub struct TcpFramer {
+    buffer: Vec<u8>,
+    start: usize,
+    options: ParseOptions,
+}
+
+impl Default for TcpFramer {
+    fn default() -> Self {
+        Self::with_options(ParseOptions::new().max_packet_size(DNS_MAX_TCP_SIZE))
+    }
+}
+
+impl TcpFramer {
+    /// Creates a framer, whose packets will be parsed using the default options.
+    /// Parsed packets can grow up to `DNS_MAX_TCP_SIZE` bytes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a framer, whose packets will be parsed using custom options.
+    pub fn with_options(options: ParseOptions) -> Self {
+        TcpFramer {
+            buffer: vec![],
+            start: 0,
+            options,
+        }
+    }
+
+    /// Appends data received from the stream.
+    pub fn push(&mut self, data: &[u8]) {
+        if self.start > 0 {
+            self.buffer.drain(..self.start);
+            self.start = 0;
+        }
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Reads a chunk of data from `reader`, and appends it.
+    /// Returns the number of bytes read, `0` meaning that the end of the stream was reached.
+    pub fn read_from(&mut self, reader: &mut impl Read) -> Result<usize, Error> {
+        let mut chunk = [0u8; 4096];
+        let len = reader.read(&mut chunk)?;
+        self.push(&chunk[..len]);
+        Ok(len)
+    }
+
+    /// Returns the number of buffered bytes that don't form a complete packet yet.
+    #[inline]
+    pub fn pending_len(&self) -> usize {
+        self.buffer.len() - self.start
+    }
+
+    /// Returns the next raw packet, if it has been completely received.
+    pub fn next_raw_packet(&mut self) -> Option<Vec<u8>> {
+        let pending = &self.buffer[self.start..];
+        if pending.len() < DNS_TCP_LENGTH_PREFIX_SIZE {
+            return None;
+        }
+        let packet_len = BigEndian::read_u16(pending) as usize;
+        let end = DNS_TCP_LENGTH_PREFIX_SIZE + packet_len;
+        if pending.len() < end {
+            return None;
+        }
+        let packet = pending[DNS_TCP_LENGTH_PREFIX_SIZE..end].to_vec();
+        self.start += end;
+        Some(packet)
+    }
+
+    /// Parses the next packet, if it has been completely received.
+    /// A packet that cannot be parsed is consumed, so that the following ones can still be read.
+    pub fn next_packet(&mut self) -> Option<Result<ParsedPacket, ParseError>> {
+        let packet = self.next_raw_packet()?;
+        Some(DNSSector::with_packet(packet).parse_with(&self.options))
+    }
+}
@@ -0,0 +1,276 @@
+use crate::compress::*;
+use crate::constants::*;
+use crate::dns_sector::*;
+use crate::errors::*;
+use crate::synth::gen;
+use byteorder::{BigEndian, ByteOrder};
+
+/// MAC algorithms supported for TSIG.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TsigAlgorithm {
+    HmacSha256,
+    HmacSha512,
+}
+
+impl TsigAlgorithm {
+    /// Returns the name of the algorithm, as used in TSIG records.
+    pub fn name(self) -> &'static [u8] {
+        match self {
+            TsigAlgorithm::HmacSha256 => b"hmac-sha256",
+            TsigAlgorithm::HmacSha512 => b"hmac-sha512",
+        }
+    }
+
+    /// Returns the algorithm with the given name, if it is supported.
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        let name = name.strip_suffix(b".").unwrap_or(name);
+        [TsigAlgorithm::HmacSha256, TsigAlgorithm::HmacSha512]
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// State of a MAC computation.
+enum Hmac {
+    Sha256(hmac_sha256::HMAC),
+    Sha512(hmac_sha512::HMAC),
+}
+
+impl Hmac {
+    fn new(algorithm: TsigAlgorithm, secret: &[u8]) -> Self {
+        match algorithm {
+            TsigAlgorithm::HmacSha256 => Hmac::Sha256(hmac_sha256::HMAC::new(secret)),
+            TsigAlgorithm::HmacSha512 => Hmac::Sha512(hmac_sha512::HMAC::new(secret)),
+        }
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        match self {
+            Hmac::Sha256(hmac) => hmac.update(input),
+            Hmac::Sha512(hmac) => hmac.update(input),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Hmac::Sha256(hmac) => hmac.finalize().to_vec(),
+            Hmac::Sha512(hmac) => hmac.finalize().to_vec(),
+        }
+    }
+}
+
+/// A shared secret used to sign and verify messages.
+#[derive(Clone, Debug)]
+pub struct TsigKey {
+    /// Name of the key, as a dot-delimited string.
+    pub name: Vec<u8>,
+    pub algorithm: TsigAlgorithm,
+    pub secret: Vec<u8>,
+}
+
+/// Relationship between a signed message and a previously signed message.
+#[derive(Copy, Clone, Debug)]
+pub enum TsigChain<'a> {
+    /// The message is a response to a request, whose MAC is given.
+    Request(&'a [u8]),
+    /// The message follows another message of a multi-message response, whose MAC is given.
+    /// Only the timers of the TSIG record are covered by the MAC of such a message.
+    Continuation(&'a [u8]),
+}
+
+/// Result of the verification of a signed message, along with its TSIG record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TsigStatus {
+    /// The message doesn't include a TSIG record.
+    Unsigned,
+    /// The MAC is valid, and the message was signed within the permitted time window.
+    Valid(TsigRecord),
+    /// The key is not in the keyring, or is not used with that algorithm.
+    BadKey(TsigRecord),
+    /// The MAC is not valid.
+    BadSig(TsigRecord),
+    /// The MAC is valid, but the message was not signed within the permitted time window.
+    BadTime(TsigRecord),
+}
+
+/// Returns the canonical (uncompressed, lower-case) raw form of a name given as a string.
+fn canonical_name(name: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut raw_name = gen::raw_name_from_str(name, None)?;
+    raw_name.make_ascii_lowercase();
+    Ok(raw_name)
+}
+
+/// Compares two MACs in constant time.
+fn mac_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// A TSIG record (RFC 8945), authenticating the message it is attached to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TsigRecord {
+    /// Name of the key, as a lower-case, dot-delimited string.
+    pub key_name: Vec<u8>,
+    /// Name of the algorithm (e.g. `hmac-sha256`), as a lower-case, dot-delimited string.
+    pub algorithm: Vec<u8>,
+    /// Signing time, in seconds since the UNIX epoch. Only the lower 48 bits are used.
+    pub time_signed: u64,
+    /// Permitted error in `time_signed`, in seconds.
+    pub fudge: u16,
+    pub mac: Vec<u8>,
+    /// Transaction ID of the message, before it was possibly rewritten by a forwarder.
+    pub original_id: u16,
+    /// Extended return code - See `Rcode::BADSIG`, `Rcode::BADKEY` and `Rcode::BADTIME`.
+    pub error: u16,
+    pub other_data: Vec<u8>,
+}
+
+impl TsigRecord {
+    /// Checks the data of an untrusted TSIG record, and returns the offsets of the end
+    /// of the algorithm name and of the end of the MAC.
+    pub(crate) fn check_rdata(rdata: &[u8]) -> Result<(usize, usize), Error> {
+        if rdata.is_empty() {
+            bail!(DSError::PacketTooSmall);
+        }
+        let algorithm_end = DNSSector::check_uncompressed_name(rdata, 0)?;
+        // time signed (6), fudge (2), MAC size (2)
+        if rdata.len() - algorithm_end < 10 {
+            bail!(DSError::PacketTooSmall);
+        }
+        let mac_len = BigEndian::read_u16(&rdata[algorithm_end + 8..]) as usize;
+        let mac_end = algorithm_end + 10 + mac_len;
+        // original ID (2), error (2), other len (2)
+        if rdata.len() < mac_end + 6 {
+            bail!(DSError::PacketTooSmall);
+        }
+        let other_len = BigEndian::read_u16(&rdata[mac_end + 4..]) as usize;
+        if rdata.len() < mac_end + 6 + other_len {
+            bail!(DSError::PacketTooSmall);
+        }
+        if rdata.len() != mac_end + 6 + other_len {
+            bail!(DSError::InvalidPacket(
+                "Unexpected data after TSIG other data"
+            ));
+        }
+        Ok((algorithm_end, mac_end))
+    }
+
+    /// Decodes the data of an untrusted TSIG record, whose owner name is `key_name`.
+    pub fn decode(mut key_name: Vec<u8>, rdata: &[u8]) -> Result<Self, Error> {
+        let (algorithm_end, mac_end) = Self::check_rdata(rdata)?;
+        key_name.make_ascii_lowercase();
+        let mut algorithm = Compress::raw_name_to_str(rdata, 0);
+        algorithm.make_ascii_lowercase();
+        let time_signed = BigEndian::read_u48(&rdata[algorithm_end..]);
+        let fudge = BigEndian::read_u16(&rdata[algorithm_end + 6..]);
+        let mac = rdata[algorithm_end + 10..mac_end].to_vec();
+        let original_id = BigEndian::read_u16(&rdata[mac_end..]);
+        let error = BigEndian::read_u16(&rdata[mac_end + 2..]);
+        let other_data = rdata[mac_end + 6..].to_vec();
+        Ok(TsigRecord {
+            key_name,
+            algorithm,
+            time_signed,
+            fudge,
+            mac,
+            original_id,
+            error,
+            other_data,
+        })
+    }
+
+    /// Encodes the record data, with an uncompressed algorithm name.
+    pub fn encode_rdata(&self) -> Result<Vec<u8>, Error> {
+        if self.mac.len() > 0xffff || self.other_data.len() > 0xffff {
+            bail!(DSError::InvalidPacket("TSIG data too long"));
+        }
+        let mut rdata = canonical_name(&self.algorithm)?;
+        let mut fields = [0u8; 10];
+        BigEndian::write_u48(&mut fields, self.time_signed & 0xffff_ffff_ffff);
+        BigEndian::write_u16(&mut fields[6..], self.fudge);
+        BigEndian::write_u16(&mut fields[8..], self.mac.len() as u16);
+        rdata.extend_from_slice(&fields);
+        rdata.extend_from_slice(&self.mac);
+        BigEndian::write_u16(&mut fields, self.original_id);
+        BigEndian::write_u16(&mut fields[2..], self.error);
+        BigEndian::write_u16(&mut fields[4..], self.other_data.len() as u16);
+        rdata.extend_from_slice(&fields[..6]);
+        rdata.extend_from_slice(&self.other_data);
+        Ok(rdata)
+    }
+
+    /// Returns `true` if the record was signed with `key`.
+    pub fn uses_key(&self, key: &TsigKey) -> bool {
+        TsigAlgorithm::from_name(&self.algorithm) == Some(key.algorithm)
+            && match (canonical_name(&self.key_name), canonical_name(&key.name)) {
+                (Ok(name), Ok(key_name)) => name == key_name,
+                _ => false,
+            }
+    }
+
+    /// Computes the MAC of `message`, that must not include the TSIG record, and whose
+    /// header must contain the original ID. The current MAC of the record is ignored.
+    pub fn compute_mac(
+        &self,
+        algorithm: TsigAlgorithm,
+        secret: &[u8],
+        message: &[u8],
+        chain: Option<TsigChain<'_>>,
+    ) -> Result<Vec<u8>, Error> {
+        let mut hmac = Hmac::new(algorithm, secret);
+        let mut mac_len = [0u8; 2];
+        match chain {
+            None => {}
+            Some(TsigChain::Request(previous_mac))
+            | Some(TsigChain::Continuation(previous_mac)) => {
+                BigEndian::write_u16(&mut mac_len, previous_mac.len() as u16);
+                hmac.update(&mac_len);
+                hmac.update(previous_mac);
+            }
+        }
+        hmac.update(message);
+        let mut timers = [0u8; 8];
+        BigEndian::write_u48(&mut timers, self.time_signed & 0xffff_ffff_ffff);
+        BigEndian::write_u16(&mut timers[6..], self.fudge);
+        if let Some(TsigChain::Continuation(_)) = chain {
+            hmac.update(&timers);
+            return Ok(hmac.finalize());
+        }
+        hmac.update(&canonical_name(&self.key_name)?);
+        let mut class_ttl = [0u8; 6];
+        BigEndian::write_u16(&mut class_ttl, Class::ANY.into());
+        hmac.update(&class_ttl);
+        hmac.update(&canonical_name(&self.algorithm)?);
+        hmac.update(&timers);
+        let mut error_other_len = [0u8; 4];
+        BigEndian::write_u16(&mut error_other_len, self.error);
+        BigEndian::write_u16(&mut error_other_len[2..], self.other_data.len() as u16);
+        hmac.update(&error_other_len);
+        hmac.update(&self.other_data);
+        Ok(hmac.finalize())
+    }
+
+    /// Checks the MAC of `message` (see `compute_mac()`) and the signing time, using a key
+    /// from `keyring`. `now` is expressed in seconds since the UNIX epoch.
+    pub fn verify(
+        self,
+        keyring: &[TsigKey],
+        message: &[u8],
+        now: u64,
+        chain: Option<TsigChain<'_>>,
+    ) -> Result<TsigStatus, Error> {
+        let key = match keyring.iter().find(|key| self.uses_key(key)) {
+            None => return Ok(TsigStatus::BadKey(self)),
+            Some(key) => key,
+        };
+        let mac = self.compute_mac(key.algorithm, &key.secret, message, chain)?;
+        if !mac_eq(&mac, &self.mac) {
+            return Ok(TsigStatus::BadSig(self));
+        }
+        let skew = now.abs_diff(self.time_signed);
+        if skew > self.fudge as u64 {
+            return Ok(TsigStatus::BadTime(self));
+        }
+        Ok(TsigStatus::Valid(self))
+    }
+}
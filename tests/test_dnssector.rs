@@ -2,6 +2,7 @@ use dnssector;
 
 mod tests {
     use super::dnssector::*;
+    use std::net::IpAddr;
 
     #[test]
     fn test_empty_packet() {
@@ -17,7 +18,7 @@ mod tests {
         let ret = dns_sector.parse();
         assert!(ret.is_err());
 
-        match ret.err().expect("error").downcast::<DSError>().unwrap() {
+        match ret.err().expect("error").kind {
             DSError::PacketTooSmall => {}
             _ => panic!(),
         }
@@ -29,7 +30,7 @@ mod tests {
         let dns_sector = DNSSector::new(data_small).unwrap();
         let ret = dns_sector.parse();
         assert!(ret.is_err());
-        match ret.err().expect("error").downcast::<DSError>().unwrap() {
+        match ret.err().expect("error").kind {
             DSError::InvalidPacket(_) => {}
             a => panic!("type: {:?}", a),
         }
@@ -41,7 +42,7 @@ mod tests {
         let dns_sector = DNSSector::new(data_small).unwrap();
         let ret = dns_sector.parse();
         assert!(ret.is_err());
-        match ret.err().expect("error").downcast::<DSError>().unwrap() {
+        match ret.err().expect("error").kind {
             DSError::InternalError(_) => {}
             a => panic!("type: {:?}", a),
         }
@@ -61,7 +62,7 @@ mod tests {
         let dns_sector = DNSSector::new(data_small).unwrap();
         let ret = dns_sector.parse();
         assert!(ret.is_err());
-        match ret.err().expect("error").downcast::<DSError>().unwrap() {
+        match ret.err().expect("error").kind {
             DSError::PacketTooSmall => {}
             a => panic!("type: {:?}", a),
         }
@@ -78,7 +79,7 @@ mod tests {
         let dns_sector = DNSSector::new(data_small).unwrap();
         let ret = dns_sector.parse();
         assert!(ret.is_err());
-        match ret.err().expect("error").downcast::<DSError>().unwrap() {
+        match ret.err().expect("error").kind {
             DSError::InvalidName(_) => {}
             a => panic!("type: {:?}", a),
         }
@@ -104,7 +105,7 @@ mod tests {
         let dns_sector = DNSSector::new(data_small).unwrap();
         let ret = dns_sector.parse();
         assert!(ret.is_err());
-        match ret.err().expect("error").downcast::<DSError>().unwrap() {
+        match ret.err().expect("error").kind {
             DSError::InvalidName(_) => {}
             a => panic!("type: {:?}", a),
         }
@@ -149,7 +150,7 @@ mod tests {
         let dns_sector = DNSSector::new(data_small).unwrap();
         let ret = dns_sector.parse();
         assert!(ret.is_err());
-        match ret.err().expect("error").downcast::<DSError>().unwrap() {
+        match ret.err().expect("error").kind {
             DSError::InvalidName("Label length too long") => {}
             DSError::InvalidPacket("A question shouldn\'t also contain answers") => {}
             a => panic!("type: {:?}", a),
@@ -169,7 +170,7 @@ mod tests {
         let dns_sector = DNSSector::new(data).unwrap();
         let ret = dns_sector.parse();
         assert!(ret.is_err());
-        match ret.err().expect("error").downcast::<DSError>().unwrap() {
+        match ret.err().expect("error").kind {
             DSError::InvalidPacket("AAAA record doesn\'t include a 16 bytes IP address") => {}
             DSError::InvalidPacket("A question shouldn\'t also contain answers") => {}
             a => panic!("type: {:?}", a),
@@ -357,9 +358,2361 @@ mod tests {
         let dns_sector = DNSSector::new(data).unwrap();
         let ret = dns_sector.parse();
         assert!(ret.is_err());
-        match ret.err().expect("error").downcast::<DSError>().unwrap() {
+        match ret.err().expect("error").kind {
             DSError::InvalidPacket(_) => {}
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn test_ensure_and_remove_edns() {
+        let mut parsed_packet = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        let query_len = parsed_packet.packet().len();
+        assert!(!parsed_packet.remove_edns().unwrap());
+
+        parsed_packet.ensure_edns(4096, 0, true).unwrap();
+        assert_eq!(parsed_packet.max_payload(), 4096);
+        assert_eq!(parsed_packet.flags() & DNS_FLAG_DO, DNS_FLAG_DO);
+        let packet = parsed_packet.into_packet();
+        assert_eq!(DNSSector::arcount(&packet), 1);
+
+        let dns_sector = DNSSector::new(packet).unwrap();
+        let mut parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(parsed_packet.max_payload(), 4096);
+        assert_eq!(parsed_packet.flags() & DNS_FLAG_DO, DNS_FLAG_DO);
+        assert!(parsed_packet.into_iter_edns().is_none());
+
+        parsed_packet.ensure_edns(1232, 0, false).unwrap();
+        assert_eq!(parsed_packet.max_payload(), 1232);
+        assert_eq!(parsed_packet.flags() & DNS_FLAG_DO, 0);
+        assert_eq!(DNSSector::arcount(parsed_packet.packet()), 1);
+
+        assert!(parsed_packet.remove_edns().unwrap());
+        assert_eq!(parsed_packet.max_payload(), DNS_DEFAULT_MAX_PAYLOAD);
+        let packet = parsed_packet.into_packet();
+        assert_eq!(packet.len(), query_len);
+        assert_eq!(DNSSector::arcount(&packet), 0);
+        let dns_sector = DNSSector::new(packet).unwrap();
+        let parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(parsed_packet.max_payload(), DNS_DEFAULT_MAX_PAYLOAD);
+    }
+
+    #[test]
+    fn test_remove_edns_followed_by_records() {
+        let data = vec![
+            0x43, 0x96, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x03, 0x63,
+            0x39, 0x78, 0x03, 0x6f, 0x72, 0x67, 0x00, 0x00, 0x10, 0x00, 0x01, 0x00, 0x00, 0x29,
+            0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x08, 0x00, 0x07, 0x00, 0x01,
+            0x18, 0x00, 0xa3, 0x05, 0x01,
+        ];
+        let dns_sector = DNSSector::new(data).unwrap();
+        let mut parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        parsed_packet
+            .insert_rr_from_string(Section::Additional, "c9x.org. 3600 IN A 1.2.3.4")
+            .unwrap();
+        assert!(parsed_packet.remove_edns().unwrap());
+        assert!(parsed_packet.into_iter_edns().is_none());
+        let packet = parsed_packet.into_packet();
+        assert_eq!(DNSSector::arcount(&packet), 1);
+        let dns_sector = DNSSector::new(packet).unwrap();
+        let mut parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(parsed_packet.flags() & DNS_FLAG_DO, 0);
+        let it = parsed_packet.into_iter_additional().unwrap();
+        assert_eq!(it.rr_type(), Type::A.into());
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn test_edns_options() {
+        let data = vec![
+            0x43, 0x96, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x03, 0x63,
+            0x39, 0x78, 0x03, 0x6f, 0x72, 0x67, 0x00, 0x00, 0x10, 0x00, 0x01, 0x00, 0x00, 0x29,
+            0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x08, 0x00, 0x07, 0x00, 0x01,
+            0x18, 0x00, 0xa3, 0x05, 0x01,
+        ];
+        let dns_sector = DNSSector::new(data).unwrap();
+        let mut parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        let client_subnet = [0x00, 0x01, 0x18, 0x00, 0xa3, 0x05, 0x01];
+        assert_eq!(
+            parsed_packet.edns_option(EdnsOption::CLIENT_SUBNET.into()),
+            Some(&client_subnet[..])
+        );
+        assert!(parsed_packet.edns_option(EdnsOption::NSID.into()).is_none());
+        {
+            let it = parsed_packet.into_iter_edns().unwrap();
+            assert_eq!(it.option_code(), EdnsOption::CLIENT_SUBNET.into());
+            assert_eq!(it.option_data(), &client_subnet[..]);
+            assert!(it.next().is_none());
+        }
+
+        parsed_packet
+            .set_edns_option(EdnsOption::NSID.into(), b"ns1")
+            .unwrap();
+        parsed_packet
+            .set_edns_option(EdnsOption::CLIENT_SUBNET.into(), &[0x00, 0x01, 0x00, 0x00])
+            .unwrap();
+        assert_eq!(parsed_packet.edns_count, 2);
+
+        let packet = parsed_packet.into_packet();
+        let dns_sector = DNSSector::new(packet).unwrap();
+        let mut parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(parsed_packet.edns_count, 2);
+        assert_eq!(
+            parsed_packet.edns_option(EdnsOption::NSID.into()),
+            Some(&b"ns1"[..])
+        );
+        assert_eq!(
+            parsed_packet.edns_option(EdnsOption::CLIENT_SUBNET.into()),
+            Some(&[0x00, 0x01, 0x00, 0x00][..])
+        );
+
+        assert!(parsed_packet
+            .remove_edns_option(EdnsOption::CLIENT_SUBNET.into())
+            .unwrap());
+        assert!(!parsed_packet
+            .remove_edns_option(EdnsOption::CLIENT_SUBNET.into())
+            .unwrap());
+        let packet = parsed_packet.into_packet();
+        let dns_sector = DNSSector::new(packet).unwrap();
+        let mut parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(parsed_packet.edns_count, 1);
+        let it = parsed_packet.into_iter_edns().unwrap();
+        assert_eq!(it.option_code(), EdnsOption::NSID.into());
+        assert_eq!(it.option_data(), b"ns1");
+    }
+
+    #[test]
+    fn test_edns_option_without_opt_rr() {
+        let mut parsed_packet = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        assert!(parsed_packet.edns_option(EdnsOption::NSID.into()).is_none());
+        assert!(parsed_packet
+            .set_edns_option(EdnsOption::NSID.into(), &[])
+            .is_err());
+        parsed_packet.ensure_edns(1232, 0, false).unwrap();
+        parsed_packet
+            .set_edns_option(EdnsOption::NSID.into(), &[])
+            .unwrap();
+        assert_eq!(
+            parsed_packet.edns_option(EdnsOption::NSID.into()),
+            Some(&[][..])
+        );
+        let packet = parsed_packet.into_packet();
+        let dns_sector = DNSSector::new(packet).unwrap();
+        let parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(parsed_packet.edns_count, 1);
+    }
+
+    #[test]
+    fn test_client_subnet() {
+        let data = vec![
+            0x43, 0x96, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x03, 0x63,
+            0x39, 0x78, 0x03, 0x6f, 0x72, 0x67, 0x00, 0x00, 0x10, 0x00, 0x01, 0x00, 0x00, 0x29,
+            0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x08, 0x00, 0x07, 0x00, 0x01,
+            0x18, 0x00, 0xa3, 0x05, 0x01,
+        ];
+        let dns_sector = DNSSector::new(data).unwrap();
+        let mut parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        let client_subnet = parsed_packet.client_subnet().unwrap().unwrap();
+        assert_eq!(client_subnet.family, DNS_ECS_FAMILY_IPV4);
+        assert_eq!(client_subnet.source_prefix, 24);
+        assert_eq!(client_subnet.scope_prefix, 0);
+        assert_eq!(client_subnet.addr, "163.5.1.0".parse::<IpAddr>().unwrap());
+
+        let ip: IpAddr = "2001:db8:1234:5678::1".parse().unwrap();
+        parsed_packet.set_client_subnet_from_ip(ip, 24, 36).unwrap();
+        assert_eq!(
+            parsed_packet.edns_option(EdnsOption::CLIENT_SUBNET.into()),
+            Some(&[0x00, 0x02, 36, 0x00, 0x20, 0x01, 0x0d, 0xb8, 0x10][..])
+        );
+        let client_subnet = parsed_packet.client_subnet().unwrap().unwrap();
+        assert_eq!(client_subnet.family, DNS_ECS_FAMILY_IPV6);
+        assert_eq!(
+            client_subnet.addr,
+            "2001:db8:1000::".parse::<IpAddr>().unwrap()
+        );
+
+        let ip: IpAddr = "192.0.2.129".parse().unwrap();
+        parsed_packet.set_client_subnet_from_ip(ip, 25, 56).unwrap();
+        assert_eq!(parsed_packet.edns_count, 1);
+        let client_subnet = parsed_packet.client_subnet().unwrap().unwrap();
+        assert_eq!(client_subnet.addr, "192.0.2.128".parse::<IpAddr>().unwrap());
+        assert_eq!(
+            ClientSubnet::decode(&client_subnet.encode().unwrap()).unwrap(),
+            client_subnet
+        );
+
+        assert!(ClientSubnet::decode(&[0x00, 0x01, 0x18, 0x00, 0xa3, 0x05]).is_err());
+        assert!(ClientSubnet::decode(&[0x00, 0x01, 0x17, 0x00, 0xa3, 0x05, 0x01]).is_err());
+        assert!(ClientSubnet::decode(&[0x00, 0x03, 0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_padding() {
+        let mut parsed_packet = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        assert!(parsed_packet.pad(PaddingPolicy::BlockLength).is_err());
+        parsed_packet.ensure_edns(1232, 0, false).unwrap();
+        parsed_packet.pad(PaddingPolicy::BlockLength).unwrap();
+        assert_eq!(parsed_packet.packet().len(), DNS_PADDING_QUERY_BLOCK_SIZE);
+        assert_eq!(parsed_packet.edns_count, 1);
+
+        parsed_packet.set_response(true);
+        parsed_packet
+            .insert_rr_from_string(Section::Answer, "example.com. 3600 IN A 192.0.2.1")
+            .unwrap();
+        parsed_packet.pad(PaddingPolicy::BlockLength).unwrap();
+        assert_eq!(
+            parsed_packet.packet().len(),
+            DNS_PADDING_RESPONSE_BLOCK_SIZE
+        );
+        assert_eq!(parsed_packet.edns_count, 1);
+
+        parsed_packet
+            .pad(PaddingPolicy::CustomBlockLength(100))
+            .unwrap();
+        assert_eq!(parsed_packet.packet().len(), 100);
+
+        parsed_packet.pad(PaddingPolicy::RandomLength(16)).unwrap();
+        let padding_len = parsed_packet
+            .edns_option(EdnsOption::PADDING.into())
+            .unwrap()
+            .len();
+        assert!(padding_len <= 16);
+
+        let packet = parsed_packet.into_packet();
+        let dns_sector = DNSSector::new(packet).unwrap();
+        let parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(parsed_packet.edns_count, 1);
+    }
+
+    #[test]
+    fn test_padding_with_records_after_opt() {
+        let query = gen::query(b"example.com", Type::NS, Class::IN).unwrap();
+        let mut response = query.to_response(Rcode::NOERROR.into()).unwrap();
+        response.ensure_edns(1232, 0, false).unwrap();
+        for rr in &[
+            "example.com. 3600 IN NS ns1.example.com.",
+            "example.com. 3600 IN NS ns2.example.com.",
+        ] {
+            response.insert_rr_from_string(Section::Answer, rr).unwrap();
+        }
+        for rr in &[
+            "ns1.example.com. 3600 IN A 192.0.2.53",
+            "ns1.example.com. 3600 IN AAAA 2001:db8::53",
+            "ns2.example.com. 3600 IN A 192.0.2.54",
+        ] {
+            response
+                .insert_rr_from_string(Section::Additional, rr)
+                .unwrap();
+        }
+        let uncompressed_len = response.packet().len();
+
+        response.pad(PaddingPolicy::CustomBlockLength(64)).unwrap();
+        let padding_len = response
+            .edns_option(EdnsOption::PADDING.into())
+            .unwrap()
+            .len();
+        assert_eq!(response.packet().len() % 64, 0);
+        assert!(response.maybe_compressed);
+        assert!(
+            response.packet().len() - padding_len - DNS_EDNS_RR_HEADER_SIZE < uncompressed_len - 32
+        );
+
+        let parsed = DNSSector::new(response.into_packet())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let types: Vec<u16> = parsed
+            .iter_additional_including_opt()
+            .map(|record| record.rr_type())
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                Type::A.into(),
+                Type::AAAA.into(),
+                Type::A.into(),
+                Type::OPT.into()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cookies() {
+        let client = [0x24, 0x64, 0xc4, 0xab, 0xcf, 0x10, 0xc9, 0x57];
+        let secret = [
+            0xe5, 0xe9, 0x73, 0xe5, 0xa6, 0xb2, 0xa4, 0x3f, 0x48, 0xe7, 0xdc, 0x84, 0x9e, 0x37,
+            0xbf, 0xcf,
+        ];
+        let client_ip: IpAddr = "198.51.100.100".parse().unwrap();
+        let now = 1559731985;
+        let config = CookieConfig::new(secret);
+
+        let mut query = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        query.ensure_edns(1232, 0, false).unwrap();
+        let client_cookie = Cookie {
+            client,
+            server: None,
+        };
+        query.set_cookie(&client_cookie).unwrap();
+        let dns_sector = DNSSector::new(query.into_packet()).unwrap();
+        let query = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        let cookie = query.cookie().unwrap().unwrap();
+        assert_eq!(cookie, client_cookie);
+        assert_eq!(
+            config.verify(&cookie, client_ip, now),
+            CookieStatus::ClientOnly
+        );
+
+        let cookie = config.response_cookie(&cookie, client_ip, now);
+        assert_eq!(
+            hex::encode(cookie.encode()),
+            "2464c4abcf10c957010000005cf79f111f8130c3eee29480"
+        );
+        assert_eq!(config.verify(&cookie, client_ip, now), CookieStatus::Valid);
+        assert_eq!(
+            config.verify(&cookie, client_ip, now + 3000),
+            CookieStatus::Valid
+        );
+        assert_eq!(
+            config.verify(&cookie, client_ip, now + 4000),
+            CookieStatus::Invalid
+        );
+        assert_eq!(
+            config.verify(&cookie, client_ip, now - 1000),
+            CookieStatus::Invalid
+        );
+        assert_eq!(
+            config.verify(&cookie, "198.51.100.101".parse().unwrap(), now),
+            CookieStatus::Invalid
+        );
+
+        let mut response = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        response.set_response(true);
+        response.ensure_edns(1232, 0, false).unwrap();
+        response.set_cookie(&cookie).unwrap();
+        response.set_badcookie().unwrap();
+        let dns_sector = DNSSector::new(response.into_packet()).unwrap();
+        let response = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(response.extended_rcode(), Rcode::BADCOOKIE.into());
+        assert_eq!(response.cookie().unwrap().unwrap(), cookie);
+
+        assert!(Cookie::decode(&client[..7]).is_err());
+        assert!(Cookie::decode(&[0u8; 12]).is_err());
+        assert!(Cookie::decode(&[0u8; 41]).is_err());
+    }
+
+    #[test]
+    fn test_extended_errors() {
+        let mut parsed_packet = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        parsed_packet.set_response(true);
+        assert!(parsed_packet
+            .add_extended_error(ExtendedError::BLOCKED, "")
+            .is_err());
+        parsed_packet.ensure_edns(1232, 0, false).unwrap();
+        assert!(parsed_packet.extended_errors().unwrap().is_empty());
+        parsed_packet
+            .add_extended_error(ExtendedError::BLOCKED, "blocked by policy")
+            .unwrap();
+        parsed_packet
+            .add_extended_error(ExtendedError::FILTERED, "")
+            .unwrap();
+        let dns_sector = DNSSector::new(parsed_packet.into_packet()).unwrap();
+        let parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(parsed_packet.edns_count, 2);
+        let extended_errors = parsed_packet.extended_errors().unwrap();
+        assert_eq!(extended_errors.len(), 2);
+        assert_eq!(extended_errors[0].error(), Some(ExtendedError::BLOCKED));
+        assert_eq!(extended_errors[0].extra_text, "blocked by policy");
+        assert_eq!(extended_errors[1].error(), Some(ExtendedError::FILTERED));
+        assert_eq!(extended_errors[1].extra_text, "");
+
+        let info = ExtendedErrorInfo::decode(&[0xff, 0x00, b'a', 0xff]).unwrap();
+        assert_eq!(info.info_code, 0xff00);
+        assert_eq!(info.error(), None);
+        assert_eq!(info.extra_text, "a\u{fffd}");
+        assert!(ExtendedErrorInfo::decode(&[0x00]).is_err());
+    }
+
+    #[test]
+    fn test_extended_rcode() {
+        let mut parsed_packet = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        parsed_packet.set_response(true);
+        parsed_packet
+            .set_extended_rcode(Rcode::NXDOMAIN.into())
+            .unwrap();
+        assert_eq!(parsed_packet.extended_rcode(), Rcode::NXDOMAIN.into());
+        assert!(parsed_packet.ext_rcode.is_none());
+
+        parsed_packet
+            .set_extended_rcode(Rcode::BADVERS.into())
+            .unwrap();
+        assert_eq!(parsed_packet.rcode(), 0);
+        assert_eq!(
+            parsed_packet.max_payload(),
+            DNS_DEFAULT_EDNS_PAYLOAD as usize
+        );
+        assert!(parsed_packet.set_extended_rcode(0x1000).is_err());
+
+        let dns_sector = DNSSector::new(parsed_packet.into_packet()).unwrap();
+        let mut parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(parsed_packet.extended_rcode(), Rcode::BADSIG.into());
+
+        parsed_packet
+            .set_extended_rcode(Rcode::SERVFAIL.into())
+            .unwrap();
+        let dns_sector = DNSSector::new(parsed_packet.into_packet()).unwrap();
+        let parsed_packet = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(parsed_packet.extended_rcode(), Rcode::SERVFAIL.into());
+        assert_eq!(parsed_packet.ext_rcode, Some(0));
+    }
+
+    #[test]
+    fn test_to_response() {
+        let data = vec![
+            0x43, 0x96, 0x01, 0x10, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x03, 0x63,
+            0x39, 0x78, 0x03, 0x6f, 0x72, 0x67, 0x00, 0x00, 0x10, 0x00, 0x01, 0x00, 0x00, 0x29,
+            0x05, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x0b, 0x00, 0x08, 0x00, 0x07, 0x00, 0x01,
+            0x18, 0x00, 0xa3, 0x05, 0x01,
+        ];
+        let dns_sector = DNSSector::new(data).unwrap();
+        let query = dns_sector.parse().expect("Valid packet couldn't be parsed");
+
+        let response = query.refused().unwrap();
+        let dns_sector = DNSSector::new(response.into_packet()).unwrap();
+        let mut response = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(response.tid(), 0x4396);
+        assert_eq!(response.extended_rcode(), Rcode::REFUSED.into());
+        assert_eq!(
+            response.flags(),
+            DNS_FLAG_QR | DNS_FLAG_RD | DNS_FLAG_CD | DNS_FLAG_DO
+        );
+        assert_eq!(response.max_payload(), DNS_DEFAULT_EDNS_PAYLOAD as usize);
+        assert_eq!(response.edns_count, 0);
+        let large_response = query
+            .to_response_with_payload(Rcode::NOERROR.into(), 4096)
+            .unwrap();
+        assert_eq!(large_response.max_payload(), 4096);
+        assert_eq!(large_response.flags() & DNS_FLAG_DO, DNS_FLAG_DO);
+        assert_eq!(
+            response.question(),
+            Some((b"c9x.org".to_vec(), Type::TXT.into(), Class::IN.into()))
+        );
+
+        let mut data = query_with_counts(1, 0);
+        data[2] = 0x20; // NOTIFY, RD=0
+        data[3] = 0x10; // CD
+        let query = DNSSector::new(data).unwrap().parse().unwrap();
+        let response = query.to_response(Rcode::NOERROR.into()).unwrap();
+        let response = DNSSector::new(response.into_packet())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(response.opcode(), 4);
+        assert_eq!(response.rcode(), Rcode::NOERROR as u8);
+        assert_eq!(response.flags(), DNS_FLAG_QR | DNS_FLAG_CD);
+
+        let mut data = query_with_counts(1, 0);
+        data[2] = 0x00; // QUERY, RD=0
+        let query = DNSSector::new(data).unwrap().parse().unwrap();
+        let response = query.servfail().unwrap();
+        assert_eq!(response.opcode(), 0);
+        assert_eq!(response.rcode(), Rcode::SERVFAIL as u8);
+        assert_eq!(response.flags(), DNS_FLAG_QR);
+
+        let query = gen::query(b"example.com", Type::AAAA, Class::IN).unwrap();
+        let soa_rr = gen::RR::from_string(
+            "example.com. 86400 IN SOA ns1.example.com. hostmaster.example.com. (1 21600 3600 604800 3600)",
+        )
+        .unwrap();
+        let response = query.nxdomain_with_soa(soa_rr.clone()).unwrap();
+        let dns_sector = DNSSector::new(response.into_packet()).unwrap();
+        let mut response = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(response.tid(), query.tid());
+        assert_eq!(response.extended_rcode(), Rcode::NXDOMAIN.into());
+        assert_eq!(response.flags(), DNS_FLAG_QR | DNS_FLAG_RD);
+        assert!(response.into_iter_answer().is_none());
+        assert_eq!(
+            response.into_iter_nameservers().unwrap().rr_type(),
+            Type::SOA.into()
+        );
+
+        let response = query.nodata_with_soa(soa_rr).unwrap();
+        assert_eq!(response.extended_rcode(), Rcode::NOERROR.into());
+        assert_eq!(DNSSector::nscount(response.packet()), 1);
+
+        let response = query.to_response(Rcode::BADVERS.into()).unwrap();
+        assert_eq!(response.extended_rcode(), Rcode::BADVERS.into());
+        assert_eq!(DNSSector::arcount(response.packet()), 1);
+        assert_eq!(query.servfail().unwrap().rcode(), Rcode::SERVFAIL.into());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let query = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        let mut response = query.to_response(Rcode::NOERROR.into()).unwrap();
+        response.ensure_edns(1232, 0, false).unwrap();
+        for rr in &[
+            "example.com. 3600 IN A 192.0.2.1",
+            "example.com. 3600 IN A 192.0.2.2",
+            "example.com. 3600 IN A 192.0.2.3",
+        ] {
+            response.insert_rr_from_string(Section::Answer, rr).unwrap();
+        }
+        response
+            .insert_rr_from_string(
+                Section::NameServers,
+                "example.com. 3600 IN NS ns1.example.com.",
+            )
+            .unwrap();
+        for rr in &[
+            "ns1.example.com. 3600 IN A 192.0.2.53",
+            "ns1.example.com. 3600 IN AAAA 2001:db8::53",
+            "ns1.example.com. 3600 IN AAAA 2001:db8::54",
+        ] {
+            response
+                .insert_rr_from_string(Section::Additional, rr)
+                .unwrap();
+        }
+
+        response.truncate_to(DNS_MAX_UNCOMPRESSED_SIZE).unwrap();
+        let compressed_len = response.packet().len();
+        assert_eq!(response.flags() & DNS_FLAG_TC, 0);
+        assert_eq!(DNSSector::arcount(response.packet()), 4);
+
+        response.truncate_to(compressed_len - 1).unwrap();
+        assert!(response.packet().len() < compressed_len);
+        assert_eq!(response.flags() & DNS_FLAG_TC, 0);
+        assert_eq!(DNSSector::ancount(response.packet()), 3);
+        assert_eq!(DNSSector::nscount(response.packet()), 1);
+        assert_eq!(DNSSector::arcount(response.packet()), 2);
+
+        let question_and_opt_len = DNS_HEADER_SIZE + 13 + 4 + 1 + DNS_OPT_RR_HEADER_SIZE;
+        response.truncate_to(question_and_opt_len).unwrap();
+        assert_eq!(response.packet().len(), question_and_opt_len);
+        assert_eq!(response.flags() & DNS_FLAG_TC, DNS_FLAG_TC);
+        assert_eq!(DNSSector::ancount(response.packet()), 0);
+        assert_eq!(DNSSector::nscount(response.packet()), 0);
+        assert_eq!(DNSSector::arcount(response.packet()), 1);
+        assert!(response.is_truncated());
+        assert!(response.truncate_to(DNS_HEADER_SIZE).is_err());
+        assert_eq!(response.packet().len(), question_and_opt_len);
+
+        let dns_sector = DNSSector::new(response.into_packet()).unwrap();
+        let response = dns_sector.parse().expect("Valid packet couldn't be parsed");
+        assert_eq!(response.max_payload(), 1232);
+    }
+
+    #[test]
+    fn test_truncate_signed_packet() {
+        let query = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        let mut response = query.to_response(Rcode::NOERROR.into()).unwrap();
+        for i in 1..=3 {
+            let rr = format!("example.com. 3600 IN A 192.0.2.{}", i);
+            response
+                .insert_rr_from_string(Section::Answer, &rr)
+                .unwrap();
+        }
+        response
+            .insert_rr_from_string(Section::Additional, "ns1.example.com. 3600 IN A 192.0.2.53")
+            .unwrap();
+        response
+            .tsig_sign(b"key.example", TsigAlgorithm::HmacSha256, b"k", 0, 300)
+            .unwrap();
+        let signed = response.packet().to_vec();
+
+        let err = response.truncate_to(DNS_HEADER_SIZE).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<DSError>(),
+            Some(&DSError::InvalidPacket(
+                "Signed packets cannot be truncated"
+            ))
+        );
+        assert_eq!(response.packet(), &signed[..]);
+        assert!(response.truncate_to(signed.len()).is_err());
+        let parsed = DNSSector::new(response.into_packet())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(parsed.tsig().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_parse_options_allowed_classes() {
+        let data = vec![
+            0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07, b'v',
+            b'e', b'r', b's', b'i', b'o', b'n', 0x04, b'b', b'i', b'n', b'd', 0x00, 0x00, 0x10,
+            0x00, 0x03,
+        ];
+        let dns_sector = DNSSector::new(data.clone()).unwrap();
+        let ret = dns_sector.parse();
+        match ret.unwrap_err().kind {
+            DSError::UnsupportedClass(class) => assert_eq!(class, Class::CH.into()),
+            a => panic!("type: {:?}", a),
+        }
+
+        let options = ParseOptions::new().allow_class(Class::CH);
+        let dns_sector = DNSSector::new(data).unwrap();
+        let mut query = dns_sector
+            .parse_with(&options)
+            .expect("Valid packet couldn't be parsed");
+        assert_eq!(
+            query.question(),
+            Some((b"version.bind".to_vec(), Type::TXT.into(), Class::CH.into()))
+        );
+
+        let mut response = query.to_response(Rcode::NOERROR.into()).unwrap();
+        response
+            .insert_rr_from_string(Section::Answer, "version.bind. 0 CH TXT \"dnssector\"")
+            .unwrap();
+        response.compress().unwrap();
+        let dns_sector = DNSSector::new(response.into_packet()).unwrap();
+        let mut response = dns_sector
+            .parse_with(&options)
+            .expect("Valid packet couldn't be parsed");
+        assert_eq!(
+            response.into_iter_answer().unwrap().rr_class(),
+            Class::CH.into()
+        );
+
+        let options = ParseOptions::new().allowed_classes(&[Class::CH]);
+        let query = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        let dns_sector = DNSSector::new(query.into_packet()).unwrap();
+        assert!(dns_sector.parse_with(&options).is_err());
+    }
+
+    fn query_with_counts(qdcount: u8, ancount: u8) -> Vec<u8> {
+        let mut data = vec![
+            0x12, 0x34, 0x01, 0x00, 0x00, qdcount, 0x00, ancount, 0x00, 0x00, 0x00, 0x00,
+        ];
+        for _ in 0..qdcount {
+            data.extend_from_slice(&[
+                0x01, b'a', 0x03, b'c', b'o', b'm', 0x00, 0x00, 0x01, 0x00, 0x01,
+            ]);
+        }
+        for _ in 0..ancount {
+            data.extend_from_slice(&[
+                0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x04, 192, 0, 2,
+                1,
+            ]);
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_options_strictness() {
+        let no_question = query_with_counts(0, 0);
+        assert!(DNSSector::new(no_question.clone())
+            .unwrap()
+            .parse()
+            .is_err());
+        let options = ParseOptions::new().allow_no_question(true);
+        let mut parsed = DNSSector::new(no_question)
+            .unwrap()
+            .parse_with(&options)
+            .unwrap();
+        assert_eq!(parsed.question(), None);
+        assert!(parsed.into_iter_question().is_none());
+
+        let two_questions = query_with_counts(2, 0);
+        assert!(DNSSector::new(two_questions.clone())
+            .unwrap()
+            .parse()
+            .is_err());
+        let options = ParseOptions::new().allow_multiple_questions(true);
+        let mut parsed = DNSSector::new(two_questions)
+            .unwrap()
+            .parse_with(&options)
+            .unwrap();
+        let mut count = 0;
+        let mut it = parsed.into_iter_question();
+        while let Some(item) = it {
+            assert_eq!(item.rr_type(), Type::A.into());
+            count += 1;
+            it = item.next();
+        }
+        assert_eq!(count, 2);
+
+        let answer_in_query = query_with_counts(1, 1);
+        assert!(DNSSector::new(answer_in_query.clone())
+            .unwrap()
+            .parse()
+            .is_err());
+        let options = ParseOptions::new().allow_records_in_queries(true);
+        let mut parsed = DNSSector::new(answer_in_query.clone())
+            .unwrap()
+            .parse_with(&options)
+            .unwrap();
+        assert_eq!(parsed.into_iter_answer().unwrap().rr_ttl(), 60);
+        let options = options.max_rr_count(Some(1));
+        let ret = DNSSector::new(answer_in_query)
+            .unwrap()
+            .parse_with(&options);
+        match ret.unwrap_err().kind {
+            DSError::InvalidPacket(_) => {}
+            a => panic!("type: {:?}", a),
+        }
+
+        let mut trailing_data = query_with_counts(1, 0);
+        let packet_len = trailing_data.len();
+        trailing_data.extend_from_slice(b"junk");
+        assert!(DNSSector::new(trailing_data.clone())
+            .unwrap()
+            .parse()
+            .is_err());
+        let options = ParseOptions::new().allow_trailing_data(true);
+        let parsed = DNSSector::new(trailing_data)
+            .unwrap()
+            .parse_with(&options)
+            .unwrap();
+        assert_eq!(parsed.packet().len(), packet_len);
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let mut data = query_with_counts(2, 1);
+        data.extend_from_slice(b"junk");
+        let mut parsed = DNSSector::new(data)
+            .unwrap()
+            .parse_lenient()
+            .expect("Lenient parsing failed");
+        assert_eq!(DNSSector::qdcount(parsed.packet()), 2);
+        assert_eq!(parsed.into_iter_answer().unwrap().rr_ttl(), 60);
+        parsed
+            .insert_rr_from_string(Section::Additional, "a.com. 60 IN A 192.0.2.2")
+            .unwrap();
+        let parsed = DNSSector::new(parsed.into_packet())
+            .unwrap()
+            .parse_lenient()
+            .expect("Lenient parsing failed");
+        assert_eq!(DNSSector::arcount(parsed.packet()), 1);
+    }
+
+    #[test]
+    fn test_multiple_questions() {
+        let options = ParseOptions::new().allow_multiple_questions(true);
+        let mut parsed = DNSSector::new(query_with_counts(1, 0))
+            .unwrap()
+            .parse_with(&options)
+            .unwrap();
+        assert_eq!(
+            parsed.question(),
+            Some((b"a.com".to_vec(), Type::A.into(), Class::IN.into()))
+        );
+        let rr = gen::RR::new_question_from_raw_name(
+            b"\x01B\x03com\x00",
+            Type::AAAA.into(),
+            Class::IN.into(),
+        );
+        parsed.insert_rr(Section::Question, rr).unwrap();
+        parsed
+            .insert_rr_from_string(Section::Additional, "b.com. 60 IN A 192.0.2.1")
+            .unwrap();
+        assert_eq!(
+            parsed.questions(),
+            vec![
+                (b"a.com".to_vec(), Type::A.into(), Class::IN.into()),
+                (b"b.com".to_vec(), Type::AAAA.into(), Class::IN.into()),
+            ]
+        );
+        assert_eq!(parsed.question().unwrap(), parsed.questions()[0]);
+        assert_eq!(
+            parsed.as_packet_ref().questions()[1],
+            (b"b.com".to_vec(), Type::AAAA.into(), Class::IN.into())
+        );
+
+        parsed.compress().unwrap();
+        let mut parsed = DNSSector::new(parsed.into_packet())
+            .unwrap()
+            .parse_with(&options)
+            .unwrap();
+        assert_eq!(DNSSector::qdcount(parsed.packet()), 2);
+        assert_eq!(
+            parsed.into_iter_additional().unwrap().name(),
+            b"b.com".to_vec()
+        );
+
+        let response = parsed.to_response(Rcode::NOERROR.into()).unwrap();
+        assert_eq!(response.questions(), parsed.questions());
+
+        parsed
+            .rename_with_raw_names(b"\x03net\x00", b"\x03com\x00", true)
+            .unwrap();
+        assert_eq!(
+            parsed.questions(),
+            vec![
+                (b"a.net".to_vec(), Type::A.into(), Class::IN.into()),
+                (b"b.net".to_vec(), Type::AAAA.into(), Class::IN.into()),
+            ]
+        );
+
+        assert_eq!(parsed.question().unwrap().0, b"a.net".to_vec());
+        parsed.into_iter_question().unwrap().delete().unwrap();
+        assert_eq!(
+            parsed.question(),
+            Some((b"b.net".to_vec(), Type::AAAA.into(), Class::IN.into()))
+        );
+        assert_eq!(
+            parsed.into_iter_additional().unwrap().name(),
+            b"b.net".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let mut data = query_with_counts(1, 1);
+        data[2] |= 0x80;
+        data[7] = 2;
+        data.extend_from_slice(&[
+            0xc0, 0x0c, 0x00, 0x1c, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x04, 192, 0, 2, 1,
+        ]);
+        let err = DNSSector::new(data).unwrap().parse().unwrap_err();
+        assert_eq!(
+            err.kind,
+            DSError::InvalidPacket("AAAA record doesn't include a 16 bytes IP address")
+        );
+        assert_eq!(err.section, Some(Section::Answer));
+        assert_eq!(err.rr_index, Some(1));
+        assert_eq!(err.rr_type, Some(Type::AAAA.into()));
+        assert_eq!(err.offset, 39);
+
+        let mut data = query_with_counts(1, 0);
+        data[DNS_HEADER_SIZE] = 0x40;
+        let err = DNSSector::new(data).unwrap().parse().unwrap_err();
+        assert_eq!(err.kind, DSError::InvalidName("Label length too long"));
+        assert_eq!(err.offset, DNS_HEADER_SIZE);
+        assert_eq!(err.section, Some(Section::Question));
+        assert_eq!(err.rr_index, Some(0));
+        assert_eq!(err.rr_type, None);
+        let err: Error = err.into();
+        assert!(err.downcast_ref::<ParseError>().is_some());
+        assert_eq!(
+            err.chain().nth(1).unwrap().downcast_ref::<DSError>(),
+            Some(&DSError::InvalidName("Label length too long"))
+        );
+
+        let err = DNSSector::new(vec![0; 4]).unwrap().parse().unwrap_err();
+        assert_eq!(err, ParseError::new(DSError::PacketTooSmall, 0));
+
+        let data = response_with_rdata(Type::CNAME, &[0x01, b'b', 0x40]);
+        let err = DNSSector::new(data).unwrap().parse().unwrap_err();
+        assert_eq!(err.kind, DSError::InvalidName("Label length too long"));
+        assert_eq!(err.offset, 23);
+        assert_eq!(err.rr_index, Some(0));
+
+        let mut data = query_with_counts(1, 1);
+        data[2] |= 0x80;
+        let packet_len = data.len();
+        data.push(0);
+        let err = DNSSector::new(data).unwrap().parse().unwrap_err();
+        assert_eq!(err.offset, packet_len);
+        assert_eq!(err.section, None);
+        assert_eq!(
+            DSError::from(err),
+            DSError::InvalidPacket("Extra data found after the last record")
+        );
+    }
+
+    #[test]
+    fn test_parse_with_diagnostics() {
+        let mut data = query_with_counts(1, 0);
+        data[2] |= 0x80;
+        data[7] = 6;
+        data[11] = 1;
+        let mut answer = |rr_type: u16, ttl: u32, rdata: &[u8]| {
+            data.extend_from_slice(&[0xc0, 0x0c]);
+            data.extend_from_slice(&rr_type.to_be_bytes());
+            data.extend_from_slice(&[0x00, 0x01]);
+            data.extend_from_slice(&ttl.to_be_bytes());
+            data.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            data.extend_from_slice(rdata);
+        };
+        answer(Type::A.into(), 60, &[192, 0, 2, 1]);
+        answer(Type::A.into(), 60, &[192, 0, 2, 1]);
+        answer(Type::A.into(), 30, &[192, 0, 2, 2]);
+        answer(Type::TXT.into(), 0x8000_0000, &[1, b'x']);
+        let mut rrsig = vec![0x00, 0x1c];
+        rrsig.extend_from_slice(&[0; 16]);
+        rrsig.extend_from_slice(&[0x00, 0x01]);
+        answer(Type::RRSIG.into(), 60, &rrsig);
+        answer(Type::DNAME.into(), 60, &[0x01, b'b', 0x00]);
+        data.extend_from_slice(&[0x00, 0x00, 0x29, 0x01, 0x00, 0, 0, 0, 0, 0x00, 0x00]);
+
+        let (mut parsed, diagnostics) = DNSSector::new(data)
+            .unwrap()
+            .parse_with_diagnostics(&ParseOptions::default())
+            .expect("Valid packet couldn't be parsed");
+        let kinds: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.section, diagnostic.rr_index, diagnostic.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (Section::Answer, 1, DiagnosticKind::DuplicateRecord),
+                (Section::Answer, 2, DiagnosticKind::InconsistentTtl),
+                (Section::Answer, 3, DiagnosticKind::TtlHighBitSet),
+                (Section::Answer, 4, DiagnosticKind::RrsigWithoutCoveredType),
+                (Section::Answer, 5, DiagnosticKind::DnameWithoutCname),
+                (Section::Additional, 0, DiagnosticKind::SmallEdnsPayload),
+            ]
+        );
+        assert_eq!(diagnostics[0].offset, 39);
+        assert_eq!(parsed.max_payload(), 256);
+        assert_eq!(parsed.into_iter_answer().unwrap().rr_ttl(), 60);
+
+        let (_, diagnostics) = DNSSector::new(query_with_counts(1, 0))
+            .unwrap()
+            .parse_with_diagnostics(&ParseOptions::default())
+            .unwrap();
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_parse_ref() {
+        let mut parsed = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        parsed.ensure_edns(1232, 0, true).unwrap();
+        parsed
+            .set_client_subnet_from_ip("192.0.2.1".parse().unwrap(), 24, 56)
+            .unwrap();
+        let mut response = parsed.to_response(Rcode::NOERROR.into()).unwrap();
+        response
+            .insert_rr_from_string(Section::Answer, "example.com. 60 IN A 192.0.2.1")
+            .unwrap();
+        response
+            .insert_rr_from_string(Section::Answer, "example.com. 30 IN A 192.0.2.2")
+            .unwrap();
+        response
+            .insert_rr_from_string(Section::Additional, "ns.example.com. 60 IN A 192.0.2.3")
+            .unwrap();
+        response.compress().unwrap();
+        let packet = response.into_packet();
+
+        let parsed_ref = DNSSector::from_slice(&packet)
+            .parse_ref()
+            .expect("Valid packet couldn't be parsed");
+        assert_eq!(parsed_ref.packet().as_ptr(), packet.as_ptr());
+        assert!(parsed_ref.is_response());
+        assert!(!parsed_ref.dnssec());
+        assert_eq!(
+            parsed_ref.question(),
+            Some((b"example.com".to_vec(), Type::A.into(), Class::IN.into()))
+        );
+        let ttls: Vec<u32> = parsed_ref.iter_answer().map(|rr| rr.rr_ttl()).collect();
+        assert_eq!(ttls, vec![60, 30]);
+        let answer = parsed_ref.iter_answer().last().unwrap();
+        assert_eq!(answer.section(), Section::Answer);
+        assert_eq!(answer.name(), b"example.com".to_vec());
+        assert_eq!(answer.rdata(), &[192, 0, 2, 2]);
+        assert_eq!(parsed_ref.iter_nameservers().count(), 0);
+        let additional: Vec<_> = parsed_ref.iter_additional().collect();
+        assert_eq!(additional.len(), 1);
+        assert_eq!(additional[0].name(), b"ns.example.com".to_vec());
+        assert_eq!(parsed_ref.max_payload(), 1232);
+        assert_eq!(parsed_ref.client_subnet().unwrap(), None);
+
+        let mut owned = parsed_ref.into_owned();
+        owned.into_iter_answer().unwrap().set_rr_ttl(10);
+        assert_eq!(
+            owned.as_packet_ref().iter_answer().next().unwrap().rr_ttl(),
+            10
+        );
+        assert_eq!(parsed_ref.iter_answer().next().unwrap().rr_ttl(), 60);
+
+        let query = parsed.into_packet();
+        let mut with_trailing_data = query.clone();
+        with_trailing_data.extend_from_slice(b"junk");
+        assert!(DNSSector::from_slice(&with_trailing_data)
+            .parse_ref()
+            .is_err());
+        let parsed_ref = DNSSector::from_slice(&with_trailing_data)
+            .parse_ref_with(&ParseOptions::lenient())
+            .unwrap();
+        assert_eq!(parsed_ref.packet(), &query[..]);
+        let client_subnet = parsed_ref.client_subnet().unwrap().unwrap();
+        assert_eq!(client_subnet.source_prefix, 24);
+    }
+
+    #[test]
+    fn test_compress_into_reuses_buffers() {
+        let mut parsed = gen::query(b"www.example.com", Type::A, Class::IN).unwrap();
+        parsed.ensure_edns(1232, 0, false).unwrap();
+        let mut response = parsed.to_response(Rcode::NOERROR.into()).unwrap();
+        response
+            .insert_rr_from_string(Section::Answer, "www.example.com. 60 IN CNAME example.com.")
+            .unwrap();
+        response
+            .insert_rr_from_string(Section::Answer, "example.com. 60 IN A 192.0.2.1")
+            .unwrap();
+        response.uncompress().unwrap();
+        let uncompressed = response.into_packet();
+
+        let mut compressed = Vec::with_capacity(512);
+        let compressed_ptr = compressed.as_ptr();
+        Compress::compress_into(&uncompressed, &mut compressed).unwrap();
+        assert_eq!(compressed, Compress::compress(&uncompressed).unwrap());
+        assert!(compressed.len() < uncompressed.len());
+        assert_eq!(compressed.as_ptr(), compressed_ptr);
+
+        let mut buffer = vec![0xff; 512];
+        let buffer_ptr = buffer.as_ptr();
+        Compress::uncompress_into(&compressed, &mut buffer).unwrap();
+        assert_eq!(buffer, uncompressed);
+        assert_eq!(buffer.as_ptr(), buffer_ptr);
+
+        assert!(Compress::compress_into(&uncompressed[..4], &mut buffer).is_err());
+    }
+
+    #[test]
+    fn test_reset_with() {
+        let query = gen::query(b"example.com", Type::A, Class::IN)
+            .unwrap()
+            .into_packet();
+        let mut parsed =
+            gen::query(b"a-much-longer-name.example.com", Type::AAAA, Class::IN).unwrap();
+        let packet_ptr = parsed.packet().as_ptr();
+
+        parsed.reset_with(&query).unwrap();
+        assert_eq!(parsed.packet(), &query[..]);
+        assert_eq!(parsed.packet().as_ptr(), packet_ptr);
+        assert_eq!(
+            parsed.question(),
+            Some((b"example.com".to_vec(), Type::A.into(), Class::IN.into()))
+        );
+
+        let err = parsed.reset_with(&query[..query.len() - 1]).unwrap_err();
+        assert_eq!(err.kind, DSError::PacketTooSmall);
+        assert_eq!(parsed.packet(), &query[..]);
+
+        let mut with_trailing_data = query.clone();
+        with_trailing_data.push(0);
+        assert!(parsed.reset_with(&with_trailing_data).is_err());
+        parsed
+            .reset_with_options(&with_trailing_data, &ParseOptions::lenient())
+            .unwrap();
+        assert_eq!(parsed.packet(), &query[..]);
+    }
+
+    fn response_with_rdata(rr_type: Type, rdata: &[u8]) -> Vec<u8> {
+        let mut data = query_with_counts(1, 0);
+        data[2] |= 0x80;
+        data[7] = 1;
+        let rr_type: u16 = rr_type.into();
+        data.extend_from_slice(&[0xc0, 0x0c, (rr_type >> 8) as u8, rr_type as u8]);
+        data.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x3c]);
+        data.extend_from_slice(&[(rdata.len() >> 8) as u8, rdata.len() as u8]);
+        data.extend_from_slice(rdata);
+        data
+    }
+
+    fn rdata_parse_error(rr_type: Type, rdata: &[u8]) -> DSError {
+        DNSSector::new(response_with_rdata(rr_type, rdata))
+            .unwrap()
+            .parse()
+            .unwrap_err()
+            .kind
+    }
+
+    #[test]
+    fn test_rdata_names_validation() {
+        let srv = [0, 10, 0, 5, 0x01, 0xbb, 1, b'b', 3, b'c', b'o', b'm', 0];
+        let mut parsed = DNSSector::new(response_with_rdata(Type::SRV, &srv))
+            .unwrap()
+            .parse()
+            .unwrap();
+        parsed.uncompress().unwrap();
+        parsed.compress().unwrap();
+        let answer = parsed.into_packet();
+        assert_eq!(&answer[answer.len() - srv.len()..], &srv[..]);
+
+        assert_eq!(
+            rdata_parse_error(Type::SRV, &[0, 10, 0, 5, 0x01, 0xbb, 0xc0, 0x0c]),
+            DSError::InvalidName("Unexpected compression")
+        );
+        assert_eq!(
+            rdata_parse_error(Type::SRV, &[0, 10, 0, 5, 0x01, 0xbb]),
+            DSError::PacketTooSmall
+        );
+        let mut srv_with_trailing_data = srv.to_vec();
+        srv_with_trailing_data.push(0);
+        assert_eq!(
+            rdata_parse_error(Type::SRV, &srv_with_trailing_data),
+            DSError::InvalidPacket("Unexpected data after name in rdata")
+        );
+        let mut overlapping_srv = response_with_rdata(Type::SRV, &srv);
+        let rdlen_offset = overlapping_srv.len() - srv.len() - 1;
+        overlapping_srv[rdlen_offset] -= 1;
+        assert_eq!(
+            DNSSector::new(overlapping_srv)
+                .unwrap()
+                .parse_lenient()
+                .unwrap_err()
+                .kind,
+            DSError::InvalidPacket("Name extends past the end of rdata")
+        );
+
+        for rr_type in &[Type::AFSDB, Type::RT, Type::KX] {
+            let rdata = [0, 1, 1, b'b', 0];
+            assert!(DNSSector::new(response_with_rdata(*rr_type, &rdata))
+                .unwrap()
+                .parse()
+                .is_ok());
+            assert_eq!(
+                rdata_parse_error(*rr_type, &[0, 1, 0xc0, 0x0c]),
+                DSError::InvalidName("Unexpected compression")
+            );
+        }
+        assert!(
+            DNSSector::new(response_with_rdata(Type::RP, &[1, b'b', 0, 0]))
+                .unwrap()
+                .parse()
+                .is_ok()
+        );
+        assert_eq!(
+            rdata_parse_error(Type::RP, &[1, b'b', 0]),
+            DSError::PacketTooSmall
+        );
+        assert_eq!(
+            rdata_parse_error(Type::PX, &[0, 1, 0, 0xc0, 0x0c]),
+            DSError::InvalidName("Unexpected compression")
+        );
+
+        let compressed_srv = [0, 10, 0, 5, 0x01, 0xbb, 0xc0, 0x0c];
+        let mut parsed = DNSSector::new(response_with_rdata(Type::SRV, &compressed_srv))
+            .unwrap()
+            .parse_lenient()
+            .unwrap();
+        let uncompressed_srv = b"\x00\x0a\x00\x05\x01\xbb\x01a\x03com\x00";
+        let renamed = Renamer::rename_with_raw_names(
+            &mut parsed,
+            b"\x01a\x03net\x00",
+            b"\x01a\x03com\x00",
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            &renamed[renamed.len() - uncompressed_srv.len()..],
+            &uncompressed_srv[..]
+        );
+        assert!(DNSSector::new(renamed).unwrap().parse_lenient().is_ok());
+        parsed.uncompress().unwrap();
+        parsed.compress().unwrap();
+        let answer = parsed.into_packet();
+        assert_eq!(
+            &answer[answer.len() - uncompressed_srv.len()..],
+            &uncompressed_srv[..]
+        );
+        assert!(DNSSector::new(answer).unwrap().parse().is_ok());
+        let mut parsed = DNSSector::new(response_with_rdata(
+            Type::PX,
+            &[0, 1, 0xc0, 0x0c, 0xc0, 0x0c],
+        ))
+        .unwrap()
+        .parse_lenient()
+        .unwrap();
+        assert!(parsed.into_iter_answer().unwrap().rdata().is_ok());
+
+        let naptr = [
+            0, 1, 0, 1, 1, b'u', 7, b'E', b'2', b'U', b'+', b's', b'i', b'p', 0, 1, b'b', 0,
+        ];
+        assert!(DNSSector::new(response_with_rdata(Type::NAPTR, &naptr))
+            .unwrap()
+            .parse()
+            .is_ok());
+        assert_eq!(
+            rdata_parse_error(Type::NAPTR, &[0, 1, 0, 1, 1, b'u', 20, b'E', 0, 0]),
+            DSError::PacketTooSmall
+        );
+        assert_eq!(
+            DNSSector::new(response_with_rdata(Type::KX, &[0, 1, 0xc0, 0x0c]))
+                .unwrap()
+                .parse_lenient()
+                .unwrap_err()
+                .kind,
+            DSError::InvalidName("Unexpected compression")
+        );
+        let compressed_naptr = [
+            0, 1, 0, 1, 1, b'u', 7, b'E', b'2', b'U', b'+', b's', b'i', b'p', 0, 0xc0, 0x0c,
+        ];
+        assert_eq!(
+            rdata_parse_error(Type::NAPTR, &compressed_naptr),
+            DSError::InvalidName("Unexpected compression")
+        );
+        let mut parsed = DNSSector::new(response_with_rdata(Type::NAPTR, &compressed_naptr))
+            .unwrap()
+            .parse_lenient()
+            .unwrap();
+        let renamed = Renamer::rename_with_raw_names(
+            &mut parsed,
+            b"\x01a\x03net\x00",
+            b"\x01a\x03com\x00",
+            false,
+        )
+        .unwrap();
+        assert!(DNSSector::new(renamed).unwrap().parse().is_ok());
+        match parsed.into_iter_answer().unwrap().rdata().unwrap() {
+            Rdata::NAPTR(naptr) => assert_eq!(naptr.replacement, b"a.com"),
+            _ => panic!("Unexpected rdata"),
+        }
+        let mut parsed = DNSSector::new(response_with_rdata(Type::NAPTR, &compressed_naptr))
+            .unwrap()
+            .parse_lenient()
+            .unwrap();
+        parsed.uncompress().unwrap();
+        let answer = parsed.into_packet();
+        assert_eq!(&answer[answer.len() - 7..], b"\x01a\x03com\x00");
+        assert_eq!(&answer[answer.len() - 24..answer.len() - 22], &[0, 22]);
+        assert!(DNSSector::new(answer).unwrap().parse().is_ok());
+
+        let mut sig = vec![0, 1, 8, 2, 0, 0, 0, 60, 0, 0, 0, 1, 0, 0, 0, 0, 0x12, 0x34];
+        sig.extend_from_slice(&[0xc0, 0x0c, 0xaa, 0xbb]);
+        assert_eq!(
+            rdata_parse_error(Type::SIG, &sig),
+            DSError::InvalidName("Unexpected compression")
+        );
+        let mut parsed = DNSSector::new(response_with_rdata(Type::SIG, &sig))
+            .unwrap()
+            .parse_lenient()
+            .unwrap();
+        parsed.uncompress().unwrap();
+        let answer = parsed.into_packet();
+        assert_eq!(&answer[answer.len() - 9..], b"\x01a\x03com\x00\xaa\xbb");
+        assert!(DNSSector::new(answer).unwrap().parse().is_ok());
+
+        let mut rrsig = vec![0, 1, 8, 2, 0, 0, 0, 60, 0, 0, 0, 1, 0, 0, 0, 0, 0x12, 0x34];
+        assert_eq!(
+            rdata_parse_error(Type::RRSIG, &rrsig),
+            DSError::PacketTooSmall
+        );
+        rrsig.extend_from_slice(&[0xc0, 0x0c, 0xaa]);
+        assert_eq!(
+            rdata_parse_error(Type::RRSIG, &rrsig),
+            DSError::InvalidName("Unexpected compression")
+        );
+        rrsig.truncate(18);
+        rrsig.extend_from_slice(&[1, b'a', 3, b'c', b'o', b'm', 0, 0xaa, 0xbb]);
+        assert!(DNSSector::new(response_with_rdata(Type::RRSIG, &rrsig))
+            .unwrap()
+            .parse()
+            .is_ok());
+
+        assert!(
+            DNSSector::new(response_with_rdata(Type::NSEC, &[1, b'b', 0, 0, 1, 0x40]))
+                .unwrap()
+                .parse()
+                .is_ok()
+        );
+        assert_eq!(
+            rdata_parse_error(Type::NSEC, &[1, b'b', 0, 0, 0]),
+            DSError::InvalidPacket("Invalid NSEC type bitmap length")
+        );
+        assert_eq!(
+            rdata_parse_error(Type::NSEC, &[1, b'b', 0, 0, 2, 0x40]),
+            DSError::InvalidPacket("Truncated NSEC type bitmap")
+        );
+    }
+
+    #[test]
+    fn test_minfo_compression() {
+        let packet = response_with_rdata(Type::MINFO, &[0xc0, 0x0c, 1, b'b', 0xc0, 0x0e]);
+        let mut parsed = DNSSector::new(packet.clone()).unwrap().parse().unwrap();
+        parsed.uncompress().unwrap();
+        let uncompressed = parsed.packet().to_vec();
+        assert_eq!(
+            &uncompressed[uncompressed.len() - 14..],
+            &[1, b'a', 3, b'c', b'o', b'm', 0, 1, b'b', 3, b'c', b'o', b'm', 0]
+        );
+        assert!(DNSSector::new(uncompressed).unwrap().parse().is_ok());
+        parsed.compress().unwrap();
+        assert_eq!(parsed.packet(), &packet[..]);
+
+        let renamed =
+            Renamer::rename_with_raw_names(&mut parsed, b"\x01x\x03net\x00", b"\x03com\x00", true)
+                .unwrap();
+        let mut renamed = DNSSector::new(renamed).unwrap().parse().unwrap();
+        renamed.uncompress().unwrap();
+        let renamed = renamed.into_packet();
+        assert_eq!(
+            &renamed[renamed.len() - 18..],
+            &[1, b'a', 1, b'x', 3, b'n', b'e', b't', 0, 1, b'b', 1, b'x', 3, b'n', b'e', b't', 0]
+        );
+    }
+
+    fn tsig_rdata(mac: &[u8]) -> Vec<u8> {
+        let mut rdata = b"\x0bHMAC-SHA256\x00".to_vec();
+        rdata.extend_from_slice(&[0x00, 0x00, 0x60, 0x00, 0x00, 0x01, 0x01, 0x2c]);
+        rdata.extend_from_slice(&[0x00, mac.len() as u8]);
+        rdata.extend_from_slice(mac);
+        rdata.extend_from_slice(&[0x12, 0x34, 0x00, 0x12, 0x00, 0x02, 0xab, 0xcd]);
+        rdata
+    }
+
+    fn with_additional_rr(
+        mut packet: Vec<u8>,
+        rr_type: Type,
+        rr_class: Class,
+        rdata: &[u8],
+    ) -> Vec<u8> {
+        packet[11] += 1;
+        let (rr_type, rr_class): (u16, u16) = (rr_type.into(), rr_class.into());
+        packet.extend_from_slice(b"\x03key\x00");
+        packet.extend_from_slice(&[(rr_type >> 8) as u8, rr_type as u8]);
+        packet.extend_from_slice(&[(rr_class >> 8) as u8, rr_class as u8, 0, 0, 0, 0]);
+        packet.extend_from_slice(&[(rdata.len() >> 8) as u8, rdata.len() as u8]);
+        packet.extend_from_slice(rdata);
+        packet
+    }
+
+    #[test]
+    fn test_tsig() {
+        let rdata = tsig_rdata(&[0x42; 32]);
+        let packet = with_additional_rr(query_with_counts(1, 0), Type::TSIG, Class::ANY, &rdata);
+        let parsed = DNSSector::new(packet.clone()).unwrap().parse().unwrap();
+        let tsig = parsed.tsig().unwrap().unwrap();
+        assert_eq!(tsig.key_name, b"key".to_vec());
+        assert_eq!(tsig.algorithm, b"hmac-sha256".to_vec());
+        assert_eq!(tsig.time_signed, 0x6000_0001);
+        assert_eq!(tsig.fudge, 300);
+        assert_eq!(tsig.mac, vec![0x42; 32]);
+        assert_eq!(tsig.original_id, 0x1234);
+        assert_eq!(tsig.error, Rcode::BADTIME.into());
+        assert_eq!(tsig.other_data, vec![0xab, 0xcd]);
+        let parsed_ref = DNSSector::from_slice(&packet).parse_ref().unwrap();
+        assert_eq!(parsed_ref.tsig().unwrap(), Some(tsig.clone()));
+
+        let mut mixed_case = packet.clone();
+        let owner_offset = mixed_case.len() - rdata.len() - DNS_RR_HEADER_SIZE - 5;
+        assert_eq!(&mixed_case[owner_offset..owner_offset + 4], b"\x03key");
+        mixed_case[owner_offset + 1..owner_offset + 4].copy_from_slice(b"KeY");
+        let parsed = DNSSector::new(mixed_case).unwrap().parse().unwrap();
+        assert_eq!(parsed.tsig().unwrap(), Some(tsig));
+
+        let parsed = DNSSector::new(query_with_counts(1, 0))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(parsed.tsig().unwrap(), None);
+
+        let not_last = with_additional_rr(packet.clone(), Type::A, Class::IN, &[192, 0, 2, 1]);
+        let err = DNSSector::new(not_last).unwrap().parse().unwrap_err();
+        assert_eq!(
+            err.kind,
+            DSError::InvalidPacket("TSIG RRs must be the last record of the additional section")
+        );
+        assert_eq!(err.rr_index, Some(0));
+        let twice = with_additional_rr(packet.clone(), Type::TSIG, Class::ANY, &rdata);
+        assert!(DNSSector::new(twice).unwrap().parse().is_err());
+
+        let mut in_answer = response_with_rdata(Type::TSIG, &rdata);
+        let class_offset = in_answer.len() - rdata.len() - 7;
+        in_answer[class_offset] = Class::ANY as u8;
+        assert_eq!(
+            DNSSector::new(in_answer).unwrap().parse().unwrap_err().kind,
+            DSError::InvalidPacket("TSIG RRs must be the last record of the additional section")
+        );
+
+        let wrong_class =
+            with_additional_rr(query_with_counts(1, 0), Type::TSIG, Class::IN, &rdata);
+        assert_eq!(
+            DNSSector::new(wrong_class)
+                .unwrap()
+                .parse()
+                .unwrap_err()
+                .kind,
+            DSError::InvalidPacket("TSIG RRs must have the ANY class")
+        );
+
+        let truncated = with_additional_rr(
+            query_with_counts(1, 0),
+            Type::TSIG,
+            Class::ANY,
+            &rdata[..rdata.len() - 1],
+        );
+        assert_eq!(
+            DNSSector::new(truncated).unwrap().parse().unwrap_err().kind,
+            DSError::PacketTooSmall
+        );
+        let mut long_rdata = rdata.clone();
+        long_rdata.push(0);
+        let long = with_additional_rr(query_with_counts(1, 0), Type::TSIG, Class::ANY, &long_rdata);
+        assert_eq!(
+            DNSSector::new(long).unwrap().parse().unwrap_err().kind,
+            DSError::InvalidPacket("Unexpected data after TSIG other data")
+        );
+    }
+
+    fn tsig_keyring() -> Vec<TsigKey> {
+        vec![
+            TsigKey {
+                name: b"other-key.example.".to_vec(),
+                algorithm: TsigAlgorithm::HmacSha512,
+                secret: b"another-secret".to_vec(),
+            },
+            TsigKey {
+                name: b"Key.Example.".to_vec(),
+                algorithm: TsigAlgorithm::HmacSha256,
+                secret: b"secret-key-0123456789".to_vec(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_tsig_sign_verify() {
+        let keyring = tsig_keyring();
+        let mut query = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        query.set_tid(0x1234);
+        let unsigned_query = query.packet().to_vec();
+        let request_mac = query
+            .tsig_sign(
+                b"key.example",
+                TsigAlgorithm::HmacSha256,
+                b"secret-key-0123456789",
+                1_700_000_000,
+                300,
+            )
+            .unwrap();
+        assert_eq!(
+            hex::encode(&request_mac),
+            "a36bd6e584623c1b337e2782c9b5f7c876192e50d8e444ca686b4f687039a46a"
+        );
+        assert!(query
+            .tsig_sign(b"key.example", TsigAlgorithm::HmacSha256, b"k", 0, 300)
+            .is_err());
+        let signed_query = query.into_packet();
+
+        let mut parsed = DNSSector::new(signed_query.clone())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let tsig = parsed.tsig().unwrap().unwrap();
+        assert_eq!(tsig.key_name, b"key.example".to_vec());
+        assert_eq!(tsig.algorithm, b"hmac-sha256".to_vec());
+        assert_eq!(tsig.mac, request_mac);
+        parsed.set_tid(0x5678);
+        assert_eq!(
+            parsed.tsig_verify(&keyring, 1_700_000_100).unwrap(),
+            TsigStatus::Valid(tsig.clone())
+        );
+        assert_eq!(parsed.packet(), &unsigned_query[..]);
+        assert_eq!(
+            parsed.tsig_verify(&keyring, 1_700_000_100).unwrap(),
+            TsigStatus::Unsigned
+        );
+
+        let verify = |packet: &[u8], keyring: &[TsigKey], now: u64| {
+            let mut parsed = DNSSector::new(packet.to_vec()).unwrap().parse().unwrap();
+            parsed.tsig_verify(keyring, now).unwrap()
+        };
+        assert_eq!(
+            verify(&signed_query, &keyring, 1_700_000_301),
+            TsigStatus::BadTime(tsig.clone())
+        );
+        assert_eq!(
+            verify(&signed_query, &keyring[..1], 1_700_000_000),
+            TsigStatus::BadKey(tsig.clone())
+        );
+        let mut tampered = signed_query.clone();
+        tampered[DNS_QUESTION_OFFSET + 1] = b'E';
+        assert_eq!(
+            verify(&tampered, &keyring, 1_700_000_000),
+            TsigStatus::BadSig(tsig)
+        );
+
+        let mut response = DNSSector::new(unsigned_query)
+            .unwrap()
+            .parse()
+            .unwrap()
+            .to_response(Rcode::NOERROR.into())
+            .unwrap();
+        response
+            .insert_rr_from_string(Section::Answer, "example.com. 60 IN A 192.0.2.1")
+            .unwrap();
+        let response_mac = response
+            .tsig_sign_chained(
+                b"key.example",
+                TsigAlgorithm::HmacSha256,
+                b"secret-key-0123456789",
+                1_700_000_001,
+                300,
+                Some(TsigChain::Request(&request_mac)),
+            )
+            .unwrap();
+        assert_eq!(
+            hex::encode(&response_mac),
+            "c4e8f71890d7dd90d654fd3dc689ad5eddc960f501fc2b1d1986d8f3036daf0f"
+        );
+        let signed_response = response.into_packet();
+        let mut parsed = DNSSector::new(signed_response.clone())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let status = parsed
+            .tsig_verify_chained(
+                &keyring,
+                1_700_000_001,
+                Some(TsigChain::Request(&request_mac)),
+            )
+            .unwrap();
+        assert!(matches!(status, TsigStatus::Valid(_)));
+        assert!(matches!(
+            verify(&signed_response, &keyring, 1_700_000_001),
+            TsigStatus::BadSig(_)
+        ));
+
+        let mut continuation = gen::query(b"example.com", Type::AXFR, Class::IN).unwrap();
+        continuation.set_response(true);
+        continuation
+            .tsig_sign_chained(
+                b"key.example",
+                TsigAlgorithm::HmacSha256,
+                b"secret-key-0123456789",
+                1_700_000_002,
+                300,
+                Some(TsigChain::Continuation(&response_mac)),
+            )
+            .unwrap();
+        let mut parsed = DNSSector::new(continuation.into_packet())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let status = parsed
+            .tsig_verify_chained(
+                &keyring,
+                1_700_000_002,
+                Some(TsigChain::Continuation(&response_mac)),
+            )
+            .unwrap();
+        assert!(matches!(status, TsigStatus::Valid(_)));
+    }
+
+    #[test]
+    fn test_tsig_sha512() {
+        let keyring = tsig_keyring();
+        let mut query = gen::query(b"example.com", Type::SOA, Class::IN).unwrap();
+        query.ensure_edns(1232, 0, false).unwrap();
+        let mac = query
+            .tsig_sign(
+                b"other-key.example",
+                TsigAlgorithm::HmacSha512,
+                b"another-secret",
+                1_700_000_000,
+                300,
+            )
+            .unwrap();
+        assert_eq!(mac.len(), 64);
+        let mut parsed = DNSSector::new(query.into_packet())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(parsed.max_payload(), 1232);
+        let status = parsed.tsig_verify(&keyring, 1_700_000_000).unwrap();
+        assert!(matches!(status, TsigStatus::Valid(_)));
+        assert_eq!(
+            TsigAlgorithm::from_name(b"HMAC-SHA512."),
+            Some(TsigAlgorithm::HmacSha512)
+        );
+        assert_eq!(TsigAlgorithm::from_name(b"hmac-md5.sig-alg.reg.int"), None);
+    }
+
+    #[test]
+    fn test_ensure_edns_signed_packet() {
+        let mut query = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        query
+            .tsig_sign(b"key.example", TsigAlgorithm::HmacSha256, b"k", 0, 300)
+            .unwrap();
+        query.ensure_edns(1232, 0, true).unwrap();
+        query.set_extended_rcode(Rcode::BADCOOKIE.into()).unwrap();
+        let parsed = DNSSector::new(query.into_packet())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(parsed.max_payload(), 1232);
+        assert_eq!(parsed.extended_rcode(), Rcode::BADCOOKIE.into());
+        assert_eq!(
+            parsed.tsig().unwrap().unwrap().key_name,
+            b"key.example".to_vec()
+        );
+        assert_eq!(
+            parsed
+                .iter_additional_including_opt()
+                .last()
+                .unwrap()
+                .rr_type(),
+            Type::TSIG.into()
+        );
+    }
+
+    #[test]
+    fn test_insert_rr_count_overflow() {
+        let mut parsed = DNSSector::new(query_with_counts(1, 0))
+            .unwrap()
+            .parse()
+            .unwrap();
+        parsed.uncompress().unwrap();
+        DNSSector::set_ancount(parsed.packet_mut(), 0xffff);
+        let packet = parsed.packet().to_vec();
+        let rr = gen::RR::from_string("a.com. 60 IN A 192.0.2.1").unwrap();
+        assert_eq!(
+            parsed
+                .insert_rr(Section::Answer, rr)
+                .unwrap_err()
+                .downcast::<DSError>()
+                .unwrap(),
+            DSError::InvalidPacket("Too many records in the same question")
+        );
+        assert_eq!(parsed.packet(), &packet[..]);
+    }
+
+    #[test]
+    fn test_insert_into_signed_packet() {
+        let mut query = gen::query(b"example.com", Type::A, Class::IN).unwrap();
+        query.ensure_edns(1232, 0, false).unwrap();
+        query
+            .tsig_sign(b"key.example", TsigAlgorithm::HmacSha256, b"k", 0, 300)
+            .unwrap();
+        query
+            .insert_rr_from_string(Section::Additional, "ns.example.com. 60 IN A 192.0.2.1")
+            .unwrap();
+        query.add_edns_option(65001, b"test").unwrap();
+        query.pad(PaddingPolicy::CustomBlockLength(128)).unwrap();
+        assert_eq!(query.packet().len() % 128, 0);
+        let parsed = DNSSector::new(query.into_packet())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let types: Vec<u16> = parsed
+            .iter_additional_including_opt()
+            .map(|record| record.rr_type())
+            .collect();
+        assert_eq!(
+            types,
+            vec![Type::A.into(), Type::OPT.into(), Type::TSIG.into()]
+        );
+        assert_eq!(parsed.edns_option(65001), Some(&b"test"[..]));
+    }
+
+    #[test]
+    fn test_tcp_framing() {
+        let mut stream = vec![];
+        let names: [&[u8]; 3] = [b"a.example.com", b"b.example.com", b"c.example.com"];
+        for name in &names {
+            let query = gen::query(name, Type::A, Class::IN).unwrap();
+            query.write_tcp(&mut stream).unwrap();
+        }
+        stream.extend_from_slice(&[0x00, 0x03, 0x01, 0x02, 0x03]);
+        let query = gen::query(b"d.example.com", Type::AAAA, Class::IN).unwrap();
+        query.write_tcp(&mut stream).unwrap();
+        let first_len = 2 + ((stream[0] as usize) << 8 | stream[1] as usize);
+
+        let mut framer = TcpFramer::new();
+        framer.push(&stream[..first_len - 1]);
+        assert!(framer.next_packet().is_none());
+        assert_eq!(framer.pending_len(), first_len - 1);
+        framer.push(&stream[first_len - 1..first_len + 1]);
+        let mut packet = framer.next_packet().unwrap().unwrap();
+        assert_eq!(packet.question().unwrap().0, b"a.example.com".to_vec());
+        assert!(framer.next_packet().is_none());
+        assert_eq!(framer.pending_len(), 1);
+
+        let mut reader = std::io::Cursor::new(&stream[first_len + 1..]);
+        while framer.read_from(&mut reader).unwrap() > 0 {}
+        let mut packet = framer.next_packet().unwrap().unwrap();
+        assert_eq!(packet.question().unwrap().0, b"b.example.com".to_vec());
+        let mut packet = framer.next_packet().unwrap().unwrap();
+        assert_eq!(packet.question().unwrap().0, b"c.example.com".to_vec());
+        assert_eq!(
+            framer.next_packet().unwrap().unwrap_err().kind,
+            DSError::PacketTooSmall
+        );
+        let mut packet = framer.next_packet().unwrap().unwrap();
+        assert_eq!(
+            packet.question(),
+            Some((
+                b"d.example.com".to_vec(),
+                Type::AAAA.into(),
+                Class::IN.into()
+            ))
+        );
+        assert!(framer.next_packet().is_none());
+        assert_eq!(framer.pending_len(), 0);
+
+        let mut framer = TcpFramer::new();
+        let mut count = 0;
+        for byte in &stream {
+            framer.push(&[*byte]);
+            while let Some(packet) = framer.next_packet() {
+                count += packet.map_or(0, |_| 1);
+            }
+        }
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn test_max_packet_size() {
+        let txt = format!("example.com. 60 IN TXT \"{}\"", "x".repeat(250));
+        let mut response = gen::query(b"example.com", Type::TXT, Class::IN)
+            .unwrap()
+            .to_response(Rcode::NOERROR.into())
+            .unwrap();
+        assert_eq!(response.max_packet_size(), DNS_MAX_UNCOMPRESSED_SIZE);
+        let mut count = 0;
+        while response
+            .insert_rr_from_string(Section::Answer, &txt)
+            .is_ok()
+        {
+            count += 1;
+        }
+        assert!(response.packet().len() <= DNS_MAX_UNCOMPRESSED_SIZE);
+        assert_eq!(count, (DNS_MAX_UNCOMPRESSED_SIZE - 29) / 274);
+
+        response.set_max_packet_size(1_000_000);
+        assert_eq!(response.max_packet_size(), DNS_MAX_TCP_SIZE);
+        while response
+            .insert_rr_from_string(Section::Answer, &txt)
+            .is_ok()
+        {
+            count += 1;
+        }
+        assert_eq!(count, (DNS_MAX_TCP_SIZE - 29) / 274);
+        let packet = response.into_packet();
+        assert!(packet.len() > DNS_MAX_TCP_SIZE - 274);
+
+        let options = ParseOptions::new().max_packet_size(DNS_MAX_TCP_SIZE);
+        let mut parsed = DNSSector::new(packet.clone())
+            .unwrap()
+            .parse_with(&options)
+            .unwrap();
+        assert_eq!(parsed.max_packet_size(), DNS_MAX_TCP_SIZE);
+        assert_eq!(
+            parsed.into_iter_answer().unwrap().rr_type(),
+            Type::TXT.into()
+        );
+        let mut parsed = DNSSector::new(packet).unwrap().parse().unwrap();
+        assert!(parsed.insert_rr_from_string(Section::Answer, &txt).is_err());
+    }
+
+    #[test]
+    fn test_grow_record_before_others() {
+        let mut response = gen::query(b"example.com", Type::A, Class::IN)
+            .unwrap()
+            .to_response(Rcode::NOERROR.into())
+            .unwrap();
+        response
+            .insert_rr_from_string(Section::Answer, "example.com. 60 IN A 192.0.2.1")
+            .unwrap();
+        response
+            .insert_rr_from_string(Section::Answer, "example.com. 60 IN A 192.0.2.2")
+            .unwrap();
+        response
+            .into_iter_answer()
+            .unwrap()
+            .set_raw_name(b"\x0ba-long-name\x07example\x03com\x00")
+            .unwrap();
+        let mut parsed = DNSSector::new(response.into_packet())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let answers: Vec<_> = parsed
+            .as_packet_ref()
+            .iter_answer()
+            .map(|rr| (rr.name(), rr.rdata().to_vec()))
+            .collect();
+        assert_eq!(
+            answers,
+            vec![
+                (b"a-long-name.example.com".to_vec(), vec![192, 0, 2, 1]),
+                (b"example.com".to_vec(), vec![192, 0, 2, 2])
+            ]
+        );
+        assert_eq!(parsed.question().unwrap().0, b"example.com".to_vec());
+    }
+
+    #[test]
+    fn test_cursor() {
+        let mut response = gen::query(b"example.com", Type::A, Class::IN)
+            .unwrap()
+            .to_response(Rcode::NOERROR.into())
+            .unwrap();
+        for rr in &[
+            "example.com. 60 IN A 192.0.2.1",
+            "example.com. 60 IN A 192.0.2.2",
+            "example.com. 60 IN A 192.0.2.3",
+        ] {
+            response.insert_rr_from_string(Section::Answer, rr).unwrap();
+        }
+        response
+            .insert_rr_from_string(Section::Additional, "example.com. 60 IN A 192.0.2.4")
+            .unwrap();
+        response.ensure_edns(1232, 0, false).unwrap();
+        response
+            .add_edns_option(EdnsOption::PADDING.into(), &[0; 4])
+            .unwrap();
+
+        let mut it = response.cursor_answer();
+        let mut count = 0;
+        while it.advance() {
+            it.set_rr_ttl(3600 + count);
+            count += 1;
+        }
+        assert_eq!(count, 3);
+        assert!(it.is_tombstone());
+        assert!(!it.advance());
+        assert!(it.is_tombstone());
+
+        let mut it = response.cursor_answer();
+        while it.advance() {
+            if it.rr_ip().unwrap() == "192.0.2.2".parse::<IpAddr>().unwrap() {
+                it.delete().unwrap();
+            }
+        }
+        let ttls: Vec<_> = response
+            .as_packet_ref()
+            .iter_answer()
+            .map(|rr| rr.rr_ttl())
+            .collect();
+        assert_eq!(ttls, vec![3600, 3602]);
+
+        assert!(!response.cursor_nameservers().advance());
+        let mut it = response.cursor_additional();
+        assert!(it.advance());
+        assert_eq!(it.rr_type(), Type::A.into());
+        assert!(!it.advance());
+        let mut it = response.cursor_additional();
+        let mut types = vec![];
+        while it.advance_including_opt() {
+            types.push(it.rr_type());
+        }
+        assert_eq!(types, vec![Type::A.into(), Type::OPT.into()]);
+        let mut it = response.cursor_edns();
+        assert!(it.advance());
+        assert_eq!(it.option_code(), EdnsOption::PADDING.into());
+        assert!(!it.advance());
+        let mut it = response.cursor_question();
+        assert!(it.advance());
+        assert_eq!(it.name(), b"example.com".to_vec());
+        assert!(!it.advance());
+    }
+
+    fn count_records(parsed_packet: &ParsedPacket, rr_type: Type) -> usize {
+        parsed_packet
+            .iter_answer()
+            .chain(parsed_packet.iter_nameservers())
+            .chain(parsed_packet.iter_additional())
+            .filter(|rr| rr.rr_type() == rr_type.into())
+            .count()
+    }
+
+    #[test]
+    fn test_shared_record_views() {
+        let mut response = gen::query(b"example.com", Type::MX, Class::IN)
+            .unwrap()
+            .to_response(Rcode::NOERROR.into())
+            .unwrap();
+        response
+            .insert_rr_from_string(Section::Answer, "example.com. 60 IN MX 10 mx.example.com")
+            .unwrap();
+        response
+            .insert_rr_from_string(
+                Section::NameServers,
+                "example.com. 120 IN NS ns.example.com",
+            )
+            .unwrap();
+        response
+            .insert_rr_from_string(Section::Additional, "mx.example.com. 30 IN A 192.0.2.1")
+            .unwrap();
+        response.ensure_edns(1232, 0, false).unwrap();
+        let parsed = DNSSector::new(response.into_packet())
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        let shared = &parsed;
+        std::thread::scope(|scope| {
+            let threads: Vec<_> = [Type::MX, Type::NS, Type::A, Type::OPT]
+                .iter()
+                .map(|&rr_type| scope.spawn(move || count_records(shared, rr_type)))
+                .collect();
+            let counts: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+            assert_eq!(counts, vec![1, 1, 1, 0]);
+        });
+
+        let question = parsed.iter_question().next().unwrap();
+        assert_eq!(question.section(), Section::Question);
+        assert_eq!(question.name(), b"example.com".to_vec());
+        assert_eq!(question.rr_type(), Type::MX.into());
+        assert_eq!(question.rr_class(), Class::IN.into());
+        assert_eq!(question.rr_ttl(), 0);
+        assert!(question.rdata().is_empty());
+        assert_eq!(
+            parsed.questions(),
+            vec![(b"example.com".to_vec(), Type::MX.into(), Class::IN.into())]
+        );
+
+        let records: Vec<_> = parsed
+            .iter_answer()
+            .chain(parsed.iter_nameservers())
+            .chain(parsed.iter_additional_including_opt())
+            .map(|rr| (rr.section(), rr.name(), rr.rr_type(), rr.rr_ttl()))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (
+                    Section::Answer,
+                    b"example.com".to_vec(),
+                    Type::MX.into(),
+                    60
+                ),
+                (
+                    Section::NameServers,
+                    b"example.com".to_vec(),
+                    Type::NS.into(),
+                    120
+                ),
+                (
+                    Section::Additional,
+                    b"mx.example.com".to_vec(),
+                    Type::A.into(),
+                    30
+                ),
+                (Section::Additional, b"".to_vec(), Type::OPT.into(), 0),
+            ]
+        );
+        let mx = parsed.iter_answer().next().unwrap();
+        assert_eq!(mx.rr_class(), Class::IN.into());
+        assert_eq!(&mx.rdata()[..2], &[0, 10]);
+        assert_eq!(
+            parsed.iter_additional().next().unwrap().rdata(),
+            &[192, 0, 2, 1]
+        );
+    }
+
+    fn decode_answer(rr_type: Type, rdata: &[u8]) -> Result<Rdata, Error> {
+        let mut parsed = DNSSector::new(response_with_rdata(rr_type, rdata))
+            .unwrap()
+            .parse_lenient()
+            .unwrap();
+        parsed.into_iter_answer().unwrap().rdata()
+    }
+
+    #[test]
+    fn test_rdata_decoding() {
+        let mut response = gen::query(b"example.com", Type::ANY, Class::IN)
+            .unwrap()
+            .to_response(Rcode::NOERROR.into())
+            .unwrap();
+        for rr in &[
+            "example.com. 60 IN A 192.0.2.1",
+            "example.com. 60 IN AAAA 2001:db8::1",
+            "example.com. 60 IN MX 10 Mail.example.com",
+            "example.com. 60 IN SOA ns.example.com. hostmaster.example.com. (1 2 3 4 5)",
+            "www.example.com. 60 IN CNAME example.com",
+            "example.com. 60 IN TXT \"hello\"",
+        ] {
+            response.insert_rr_from_string(Section::Answer, rr).unwrap();
+        }
+        response.compress().unwrap();
+        assert!(response.packet().windows(2).any(|w| w == [0xc0, 0x0c]));
+        let mut decoded = vec![];
+        let mut it = response.cursor_answer();
+        while it.advance() {
+            decoded.push(it.rdata().unwrap());
+        }
+        assert_eq!(
+            decoded,
+            vec![
+                Rdata::A("192.0.2.1".parse().unwrap()),
+                Rdata::AAAA("2001:db8::1".parse().unwrap()),
+                Rdata::MX(MxRdata {
+                    preference: 10,
+                    exchange: b"Mail.example.com".to_vec(),
+                }),
+                Rdata::SOA(SoaRdata {
+                    mname: b"ns.example.com".to_vec(),
+                    rname: b"hostmaster.example.com".to_vec(),
+                    serial: 1,
+                    refresh: 2,
+                    retry: 3,
+                    expire: 4,
+                    minimum: 5,
+                }),
+                Rdata::CNAME(b"example.com".to_vec()),
+                Rdata::TXT(vec![b"hello".to_vec()]),
+            ]
+        );
+        assert_eq!(decoded[2].rr_type(), Type::MX.into());
+
+        assert_eq!(
+            decode_answer(Type::SRV, b"\x00\x0a\x00\x05\x01\xbb\x01b\x03com\x00").unwrap(),
+            Rdata::SRV(SrvRdata {
+                priority: 10,
+                weight: 5,
+                port: 443,
+                target: b"b.com".to_vec(),
+            })
+        );
+        assert_eq!(
+            decode_answer(
+                Type::NAPTR,
+                b"\x00\x01\x00\x02\x01S\x07SIP+D2U\x00\x04_sip\x00"
+            )
+            .unwrap(),
+            Rdata::NAPTR(NaptrRdata {
+                order: 1,
+                preference: 2,
+                flags: b"S".to_vec(),
+                services: b"SIP+D2U".to_vec(),
+                regexp: vec![],
+                replacement: b"_sip".to_vec(),
+            })
+        );
+        assert_eq!(
+            decode_answer(Type::CAA, b"\x00\x05issueca.example").unwrap(),
+            Rdata::CAA(CaaRdata {
+                flags: 0,
+                tag: b"issue".to_vec(),
+                value: b"ca.example".to_vec(),
+            })
+        );
+        assert_eq!(
+            decode_answer(Type::DS, b"\x30\x39\x08\x02\xaa\xbb").unwrap(),
+            Rdata::DS(DsRdata {
+                key_tag: 12345,
+                algorithm: 8,
+                digest_type: 2,
+                digest: vec![0xaa, 0xbb],
+            })
+        );
+        assert_eq!(
+            decode_answer(Type::DNSKEY, b"\x01\x01\x03\x0d\x01\x02\x03").unwrap(),
+            Rdata::DNSKEY(DnskeyRdata {
+                flags: 257,
+                protocol: 3,
+                algorithm: 13,
+                public_key: vec![1, 2, 3],
+            })
+        );
+        let mut rrsig = vec![0, 1, 13, 2, 0, 0, 0x0e, 0x10];
+        rrsig.extend_from_slice(&[0x60, 0, 0, 2, 0x60, 0, 0, 1, 0x30, 0x39]);
+        rrsig.extend_from_slice(b"\x01a\x03com\x00\xde\xad");
+        assert_eq!(
+            decode_answer(Type::RRSIG, &rrsig).unwrap(),
+            Rdata::RRSIG(RrsigRdata {
+                type_covered: Type::A.into(),
+                algorithm: 13,
+                labels: 2,
+                original_ttl: 3600,
+                expiration: 0x6000_0002,
+                inception: 0x6000_0001,
+                key_tag: 12345,
+                signer_name: b"a.com".to_vec(),
+                signature: vec![0xde, 0xad],
+            })
+        );
+        assert_eq!(
+            decode_answer(
+                Type::NSEC,
+                b"\x01b\x03com\x00\x00\x04\x40\x01\x00\x08\x01\x01\x40"
+            )
+            .unwrap(),
+            Rdata::NSEC(NsecRdata {
+                next_domain_name: b"b.com".to_vec(),
+                types: vec![
+                    Type::A.into(),
+                    Type::MX.into(),
+                    Type::AAAA.into(),
+                    Type::CAA.into()
+                ],
+            })
+        );
+        assert_eq!(
+            decode_answer(
+                Type::NSEC3,
+                b"\x01\x00\x00\x0a\x02\xab\xcd\x03\x01\x02\x03\x00\x01\x40"
+            )
+            .unwrap(),
+            Rdata::NSEC3(Nsec3Rdata {
+                hash_algorithm: 1,
+                flags: 0,
+                iterations: 10,
+                salt: vec![0xab, 0xcd],
+                next_hashed_owner_name: vec![1, 2, 3],
+                types: vec![Type::A.into()],
+            })
+        );
+        assert_eq!(
+            decode_answer(Type::TLSA, b"\x03\x01\x01\x42\x43").unwrap(),
+            Rdata::TLSA(TlsaRdata {
+                cert_usage: 3,
+                selector: 1,
+                matching_type: 1,
+                cert_data: vec![0x42, 0x43],
+            })
+        );
+        assert_eq!(
+            decode_answer(Type::SSHFP, b"\x04\x02\x42").unwrap(),
+            Rdata::SSHFP(SshfpRdata {
+                algorithm: 4,
+                fingerprint_type: 2,
+                fingerprint: vec![0x42],
+            })
+        );
+        let svcb = SvcbRdata {
+            priority: 1,
+            target: vec![],
+            params: vec![(1, b"\x02h2".to_vec()), (3, vec![0x01, 0xbb])],
+        };
+        let svcb_rdata = b"\x00\x01\x00\x00\x01\x00\x03\x02h2\x00\x03\x00\x02\x01\xbb";
+        assert_eq!(
+            decode_answer(Type::HTTPS, svcb_rdata).unwrap(),
+            Rdata::HTTPS(svcb.clone())
+        );
+        assert_eq!(
+            decode_answer(Type::SVCB, svcb_rdata).unwrap(),
+            Rdata::SVCB(svcb)
+        );
+        assert_eq!(
+            decode_answer(Type::HINFO, b"\x03CPU\x02OS").unwrap(),
+            Rdata::Unknown(Type::HINFO.into(), b"\x03CPU\x02OS".to_vec())
+        );
+        assert_eq!(
+            decode_answer(Type::MINFO, b"\x01r\xc0\x0c\x01e\xc0\x0c").unwrap(),
+            Rdata::Unknown(
+                Type::MINFO.into(),
+                b"\x01r\x01a\x03com\x00\x01e\x01a\x03com\x00".to_vec()
+            )
+        );
+
+        for (rr_type, rdata) in &[
+            (Type::CAA, &b"\x00\x05iss"[..]),
+            (Type::SSHFP, b"\x04"),
+            (Type::NSEC3, b"\x01\x00\x00\x0a\x02\xab"),
+            (Type::HTTPS, b"\x00\x01\x00\x00\x01\x00\x03\x02"),
+        ] {
+            let err = decode_answer(*rr_type, rdata).unwrap_err();
+            assert_eq!(err.downcast_ref(), Some(&DSError::PacketTooSmall));
+        }
+        let err = decode_answer(Type::NSEC3, b"\x01\x00\x00\x0a\x00\x00\x00\x00").unwrap_err();
+        assert_eq!(
+            err.downcast_ref(),
+            Some(&DSError::InvalidPacket("Invalid NSEC type bitmap length"))
+        );
+        let err = decode_answer(Type::HTTPS, b"\x00\x01\xc0\x0c").unwrap_err();
+        assert_eq!(
+            err.downcast_ref(),
+            Some(&DSError::InvalidName("Unexpected compression"))
+        );
+    }
+
+    #[test]
+    fn test_set_rdata() {
+        let mut response = gen::query(b"www.example.com", Type::A, Class::IN)
+            .unwrap()
+            .to_response(Rcode::NOERROR.into())
+            .unwrap();
+        for rr in &[
+            "www.example.com. 60 IN CNAME example.com",
+            "example.com. 60 IN A 192.0.2.1",
+            "example.com. 60 IN MX 10 mail.example.com",
+        ] {
+            response.insert_rr_from_string(Section::Answer, rr).unwrap();
+        }
+        response
+            .insert_rr_from_string(Section::Additional, "mail.example.com. 60 IN A 192.0.2.2")
+            .unwrap();
+        response.ensure_edns(1232, 0, false).unwrap();
+        response.compress().unwrap();
+
+        let cname = Rdata::CNAME(b"a-much-longer-target.example.net".to_vec());
+        let mx = Rdata::MX(MxRdata {
+            preference: 20,
+            exchange: b"mx.example.com".to_vec(),
+        });
+        let mut it = response.cursor_answer();
+        while it.advance() {
+            if it.rr_type() == Type::CNAME.into() {
+                it.set_rdata(&cname).unwrap();
+            } else if it.rr_type() == Type::MX.into() {
+                it.set_rdata(&mx).unwrap();
+            }
+        }
+        let mut parsed = DNSSector::new(response.into_packet())
+            .unwrap()
+            .parse()
+            .unwrap();
+        let mut decoded = vec![];
+        let mut it = parsed.cursor_answer();
+        while it.advance() {
+            decoded.push((it.name(), it.rr_ttl(), it.rdata().unwrap()));
+        }
+        assert_eq!(
+            decoded,
+            vec![
+                (b"www.example.com".to_vec(), 60, cname.clone()),
+                (
+                    b"example.com".to_vec(),
+                    60,
+                    Rdata::A("192.0.2.1".parse().unwrap())
+                ),
+                (b"example.com".to_vec(), 60, mx.clone()),
+            ]
+        );
+        let additional = parsed.into_iter_additional().unwrap();
+        assert_eq!(additional.name(), b"mail.example.com".to_vec());
+        assert_eq!(
+            additional.rdata().unwrap(),
+            Rdata::A("192.0.2.2".parse().unwrap())
+        );
+        assert_eq!(parsed.max_payload(), 1232);
+
+        let mut it = parsed.into_iter_answer().unwrap();
+        assert_eq!(
+            it.set_rdata(&mx).unwrap_err().downcast_ref(),
+            Some(&DSError::InvalidPacket(
+                "Record data doesn't match the record type"
+            ))
+        );
+        let long_txt = Rdata::TXT(vec![vec![b'x'; 256]]);
+        assert!(it.set_rdata(&long_txt).is_err());
+        assert!(it
+            .set_rdata(&Rdata::Unknown(Type::CNAME.into(), b"\x01a".to_vec()))
+            .is_err());
+        for compressed in &[&b"\xc0\x07"[..], b"\x01a\xc0\x0c"] {
+            assert!(it
+                .set_rdata(&Rdata::Unknown(Type::CNAME.into(), compressed.to_vec()))
+                .is_err());
+        }
+        it.set_rdata(&Rdata::Unknown(Type::CNAME.into(), b"\x01a\x00".to_vec()))
+            .unwrap();
+        assert_eq!(it.rdata().unwrap(), Rdata::CNAME(b"a".to_vec()));
+        let mut it = parsed.into_iter_additional_including_opt().unwrap();
+        assert!(it.advance_including_opt());
+        assert_eq!(it.rr_type(), Type::OPT.into());
+        assert_eq!(
+            it.set_rdata(&Rdata::Unknown(Type::OPT.into(), vec![]))
+                .unwrap_err()
+                .downcast_ref(),
+            Some(&DSError::PropertyNotFound)
+        );
+
+        let mut txt_response = gen::query(b"example.com", Type::TXT, Class::IN)
+            .unwrap()
+            .to_response(Rcode::NOERROR.into())
+            .unwrap();
+        txt_response
+            .insert_rr_from_string(Section::Answer, "example.com. 60 IN TXT \"x\"")
+            .unwrap();
+        let huge_txt = Rdata::TXT(vec![vec![b'x'; 255]; 40]);
+        assert_eq!(
+            txt_response
+                .into_iter_answer()
+                .unwrap()
+                .set_rdata(&huge_txt)
+                .unwrap_err()
+                .downcast_ref(),
+            Some(&DSError::PacketTooLarge)
+        );
+        txt_response.set_max_packet_size(DNS_MAX_TCP_SIZE);
+        txt_response
+            .into_iter_answer()
+            .unwrap()
+            .set_rdata(&huge_txt)
+            .unwrap();
+        assert_eq!(
+            txt_response.into_iter_answer().unwrap().rdata().unwrap(),
+            huge_txt
+        );
+    }
+
+    #[test]
+    fn test_rdata_encoding() {
+        let nsec = b"\x01b\x03com\x00\x00\x04\x40\x01\x00\x08\x01\x01\x40";
+        let decoded = Rdata::decode(Type::NSEC.into(), nsec).unwrap();
+        assert_eq!(decoded.encode().unwrap(), nsec.to_vec());
+        let shuffled = Rdata::NSEC(NsecRdata {
+            next_domain_name: b"b.com".to_vec(),
+            types: vec![
+                Type::CAA.into(),
+                Type::AAAA.into(),
+                Type::A.into(),
+                Type::MX.into(),
+                Type::A.into(),
+            ],
+        });
+        assert_eq!(shuffled.encode().unwrap(), nsec.to_vec());
+
+        let values = vec![
+            Rdata::A("192.0.2.1".parse().unwrap()),
+            Rdata::AAAA("2001:db8::1".parse().unwrap()),
+            Rdata::NS(b"ns.example.com".to_vec()),
+            Rdata::DNAME(b"example.net".to_vec()),
+            Rdata::SOA(SoaRdata {
+                mname: b"ns.example.com".to_vec(),
+                rname: b"hostmaster.example.com".to_vec(),
+                serial: 1,
+                refresh: 2,
+                retry: 3,
+                expire: 4,
+                minimum: 5,
+            }),
+            Rdata::TXT(vec![b"hello".to_vec(), vec![], b"world".to_vec()]),
+            Rdata::SRV(SrvRdata {
+                priority: 10,
+                weight: 5,
+                port: 443,
+                target: vec![],
+            }),
+            Rdata::NAPTR(NaptrRdata {
+                order: 1,
+                preference: 2,
+                flags: b"S".to_vec(),
+                services: b"SIP+D2U".to_vec(),
+                regexp: vec![],
+                replacement: b"_sip._udp.example.com".to_vec(),
+            }),
+            Rdata::CAA(CaaRdata {
+                flags: 128,
+                tag: b"issue".to_vec(),
+                value: b"ca.example".to_vec(),
+            }),
+            Rdata::NSEC3(Nsec3Rdata {
+                hash_algorithm: 1,
+                flags: 1,
+                iterations: 0,
+                salt: vec![],
+                next_hashed_owner_name: vec![0x42; 20],
+                types: vec![Type::NS.into(), Type::SOA.into(), Type::TA.into()],
+            }),
+            Rdata::HTTPS(SvcbRdata {
+                priority: 0,
+                target: b"svc.example.com".to_vec(),
+                params: vec![],
+            }),
+            Rdata::Unknown(Type::HINFO.into(), b"\x03CPU\x02OS".to_vec()),
+        ];
+        for value in values {
+            let encoded = value.encode().unwrap();
+            assert_eq!(Rdata::decode(value.rr_type(), &encoded).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_rdata_name_escaping() {
+        let mut raw = b"\x03a.b\x03\xc3 \\\x3f".to_vec();
+        raw.extend_from_slice(&[b'x'; 63]);
+        raw.extend_from_slice(b"\x03com\x00");
+        let decoded = decode_answer(Type::NS, &raw).unwrap();
+        let mut name = b"a\\.b.\\195\\032\\\\.".to_vec();
+        name.extend_from_slice(&[b'x'; 63]);
+        name.extend_from_slice(b".com");
+        assert_eq!(decoded, Rdata::NS(name));
+        assert_eq!(decoded.encode().unwrap(), raw);
+
+        let encoded = Rdata::CNAME(b"\\097\\.b.com.".to_vec()).encode().unwrap();
+        assert_eq!(encoded, b"\x03a.b\x03com\x00".to_vec());
+        assert_eq!(Rdata::NS(b".".to_vec()).encode().unwrap(), vec![0]);
+        for invalid in &[&b"a\\256.com"[..], b"a\\1.com", b"a\\", b"a..com"] {
+            assert!(Rdata::NS(invalid.to_vec()).encode().is_err());
+        }
+        assert!(Rdata::NS(vec![b'x'; 64]).encode().is_err());
+    }
 }
@@ -125,4 +125,15 @@ mod tests {
         ];
         assert_eq!(&packet[2..], &expected[2..]);
     }
+
+    #[test]
+    fn test_gen_classes() {
+        assert!(RR::from_string("version.bind. 0 CH TXT \"dnssector\"").is_ok());
+        assert!(RR::from_string("version.bind. 0 chaos TXT \"dnssector\"").is_ok());
+        assert!(RR::from_string("example.com. 0 HS A 1.2.3.4").is_ok());
+        assert!(RR::from_string("example.com. 0 NONE A 1.2.3.4").is_ok());
+        assert!(RR::from_string("example.com. 0 ANY A 1.2.3.4").is_ok());
+        assert!(RR::from_string("example.com. 0 XX A 1.2.3.4").is_err());
+        assert_eq!(Class::from_string("hesiod").unwrap(), Class::HS);
+    }
 }